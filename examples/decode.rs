@@ -1,6 +1,6 @@
 use std::fs::File;
 
-use giflar::decoder::parse;
+use giflar::decoder::{decode, parse};
 
 fn main() {
     let file = File::open("images/earth.gif").unwrap();
@@ -8,4 +8,13 @@ fn main() {
     let gif = parse(file).unwrap();
 
     println!("Parsed: {:#}", gif);
+
+    let frames = decode(gif).unwrap();
+    let last = frames.last().unwrap();
+
+    println!(
+        "Decoded {} frames, last starts at {:?}",
+        frames.len(),
+        last.start
+    );
 }
@@ -1,3 +1,29 @@
+use std::fs::File;
+
+use giflar::encoder::Encoder;
+
 fn main() {
-    println!("Encoding here!")
+    let (width, height) = (64u16, 64u16);
+    let mut encoder = Encoder::new(width, height);
+    encoder.loop_count(0);
+
+    for step in 0..16u16 {
+        let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+
+        for y in 0..height {
+            for x in 0..width {
+                let r = (x * 4) as u8;
+                let g = (y * 4) as u8;
+                let b = (step * 16) as u8;
+                rgba.extend([r, g, b, 255]);
+            }
+        }
+
+        encoder.add_frame(&rgba, 5).unwrap();
+    }
+
+    let path = std::env::temp_dir().join("giflar-encoded.gif");
+    encoder.write_to(File::create(&path).unwrap()).unwrap();
+
+    println!("Encoded {}", path.display());
 }
@@ -1,7 +1,8 @@
 use nom::{
     IResult, Parser,
+    branch::alt,
     bytes::complete::{tag, take},
-    combinator::{map, value},
+    combinator::map,
     number::le_u16,
     sequence::{delimited, preceded},
 };
@@ -11,7 +12,7 @@ use crate::{
     decoder::common::{byte, constant, packed_byte as packed_field},
     gif::extension::{
         ApplicationExtension, CommentExtension, GraphicControlExtension, NetScapeExtension,
-        PlainTextExtension,
+        PlainTextExtension, UnknownApplicationExtension,
     },
 };
 
@@ -52,35 +53,54 @@ fn transparent_color_idx(input: &[u8]) -> IResult<&[u8], u8> {
 pub fn plain_text_extension(input: &[u8]) -> IResult<&[u8], PlainTextExtension> {
     let start_signature = (ExtensionIntroducer, PlainTextLabel);
     let (rest, len) = preceded(start_signature, block_len).parse(input)?;
-    let chomp = take(len);
+    let header = take(len).map(<[u8]>::to_vec);
 
-    value(PlainTextExtension, (chomp, sub_blocks)).parse(rest)
+    map((header, sub_blocks), PlainTextExtension::from_tuple).parse(rest)
 }
 
 pub fn comment_extension(input: &[u8]) -> IResult<&[u8], CommentExtension> {
     let start_signature = (ExtensionIntroducer, CommentExtensionLabel);
-    value(CommentExtension, (start_signature, sub_blocks)).parse(input)
+    preceded(start_signature, sub_blocks)
+        .map(CommentExtension::new)
+        .parse(input)
 }
 
-/// Only NETSCAPE2.0 is recognized
+/// NETSCAPE2.0 is recognized, other applications are kept as unknown
 pub fn application_extension(input: &[u8]) -> IResult<&[u8], ApplicationExtension> {
-    let netscape = b"NETSCAPE2.0";
     let start_signature = (
         ExtensionIntroducer,
         ApplicationExtensionLabel,
         tag([0x0B].as_ref()),
-        tag(netscape.as_ref()),
     );
 
-    preceded(start_signature, sub_blocks)
+    preceded(
+        start_signature,
+        alt((netscape_extension, unknown_extension)),
+    )
+    .parse(input)
+}
+
+fn netscape_extension(input: &[u8]) -> IResult<&[u8], ApplicationExtension> {
+    preceded(tag(NetScapeExtension::IDENTIFIER.as_ref()), sub_blocks)
         .map(NetScapeExtension::new)
         .map(ApplicationExtension::NetScape)
         .parse(input)
 }
 
+fn unknown_extension(input: &[u8]) -> IResult<&[u8], ApplicationExtension> {
+    let identifier = take(8usize).map(|bytes: &[u8]| bytes.try_into().expect("8 bytes"));
+    let authentication_code = take(3usize).map(|bytes: &[u8]| bytes.try_into().expect("3 bytes"));
+
+    (identifier, authentication_code, sub_blocks)
+        .map(UnknownApplicationExtension::from_tuple)
+        .map(ApplicationExtension::Other)
+        .parse(input)
+}
+
 #[cfg(test)]
 mod should {
     use super::*;
+    use crate::gif::SubBlock;
 
     #[test]
     fn parse_application_ext() {
@@ -88,9 +108,27 @@ mod should {
 
         let (rem, app_ext) = application_extension(data).unwrap();
 
-        let ApplicationExtension::NetScape(netscape) = app_ext;
+        let ApplicationExtension::NetScape(netscape) = app_ext else {
+            panic!("expected NETSCAPE2.0 extension");
+        };
 
         assert!(rem.is_empty());
         assert_eq!(netscape.sub_blocks.len(), 1);
     }
+
+    #[test]
+    fn keep_unknown_application_ext() {
+        let data = b"\x21\xFF\x0BXMP DataXMP\x02ab\x00";
+
+        let (rem, app_ext) = application_extension(data).unwrap();
+
+        let ApplicationExtension::Other(other) = app_ext else {
+            panic!("expected unknown extension");
+        };
+
+        assert!(rem.is_empty());
+        assert_eq!(&other.identifier, b"XMP Data");
+        assert_eq!(&other.authentication_code, b"XMP");
+        assert_eq!(other.sub_blocks, vec![SubBlock(b"ab".to_vec())]);
+    }
 }
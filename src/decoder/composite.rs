@@ -0,0 +1,156 @@
+use super::{DecodeError, decode_indices};
+use crate::gif::{Block, Gif, ImageBlock, ImageContent, Rect, Rgba, extension::DisposalMethod};
+
+/// Fully transparent canvas pixel
+pub const TRANSPARENT: Rgba = [0, 0, 0, 0];
+
+/// A frame as it appears on screen after every earlier frame was drawn and
+/// disposed
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompositedFrame {
+    /// Canvas pixels in row-major order
+    pub pixels: Vec<Rgba>,
+    /// Delay in hundredths of a second
+    pub delay_time: u16,
+    /// Index of the source block in [`Gif::blocks`]
+    pub block_idx: usize,
+}
+
+/// Draws the image frames of a GIF one after another onto a transparent
+/// canvas of the logical screen size
+pub struct Compositor<'a> {
+    gif: &'a Gif,
+    canvas: Vec<Rgba>,
+    next_block: usize,
+    disposal: Option<Disposal>,
+}
+
+/// How to dispose the last drawn frame before drawing the next one
+struct Disposal {
+    rect: Rect,
+    method: DisposalMethod,
+    previous: Option<Vec<Rgba>>,
+}
+
+impl<'a> Compositor<'a> {
+    pub fn new(gif: &'a Gif) -> Self {
+        let pixel_count = gif.lsd.canvas_width as usize * gif.lsd.canvas_height as usize;
+
+        Self {
+            gif,
+            canvas: vec![TRANSPARENT; pixel_count],
+            next_block: 0,
+            disposal: None,
+        }
+    }
+
+    fn draw(
+        &mut self,
+        block_idx: usize,
+        block: &ImageBlock,
+    ) -> Result<CompositedFrame, DecodeError> {
+        let ImageContent::Image(image) = &block.content else {
+            unreachable!("only image blocks are drawn");
+        };
+
+        self.dispose();
+
+        let color_table =
+            image
+                .lct
+                .as_ref()
+                .or(self.gif.gct.as_ref())
+                .ok_or(DecodeError::InvalidData(
+                    "image has no color table defined".into(),
+                ))?;
+        let gce = block.gce.as_ref();
+        let transparent_idx = gce.and_then(|gce| gce.transparent_color_idx());
+        let method = gce.map(|gce| gce.disposal_method()).unwrap_or_default();
+
+        let rect = image.descriptor.rect();
+        self.disposal = Some(Disposal {
+            rect,
+            method,
+            previous: (method == DisposalMethod::RestorePrevious).then(|| self.canvas.clone()),
+        });
+
+        let indices = decode_indices(image)?;
+        let canvas_width = self.gif.lsd.canvas_width as u32;
+
+        for (pixel_idx, color_idx) in indices.iter().enumerate() {
+            if Some(*color_idx) == transparent_idx {
+                continue;
+            }
+
+            let x = rect.left as u32 + (pixel_idx % rect.width as usize) as u32;
+            let y = rect.top as u32 + (pixel_idx / rect.width as usize) as u32;
+            if x >= canvas_width || y >= self.gif.lsd.canvas_height as u32 {
+                continue;
+            }
+
+            let color = color_table.get(*color_idx).copied().unwrap_or_default();
+            self.canvas[(y * canvas_width + x) as usize] = [color.r, color.g, color.b, 255];
+        }
+
+        Ok(CompositedFrame {
+            pixels: self.canvas.clone(),
+            delay_time: gce.map_or(0, |gce| gce.delay_time()),
+            block_idx,
+        })
+    }
+
+    fn dispose(&mut self) {
+        let Some(disposal) = self.disposal.take() else {
+            return;
+        };
+
+        match disposal.method {
+            DisposalMethod::Unspecified | DisposalMethod::Keep => {}
+            DisposalMethod::RestoreBackground => {
+                let canvas_width = self.gif.lsd.canvas_width as usize;
+                let canvas = Rect::new(0, 0, self.gif.lsd.canvas_width, self.gif.lsd.canvas_height);
+
+                if let Some(rect) = disposal.rect.intersection(&canvas) {
+                    for y in rect.top as usize..rect.bottom() as usize {
+                        let row = y * canvas_width;
+                        self.canvas[row + rect.left as usize..row + rect.right() as usize]
+                            .fill(TRANSPARENT);
+                    }
+                }
+            }
+            DisposalMethod::RestorePrevious => {
+                if let Some(previous) = disposal.previous {
+                    self.canvas = previous;
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for Compositor<'_> {
+    type Item = Result<CompositedFrame, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(block) = self.gif.blocks.get(self.next_block) {
+            let block_idx = self.next_block;
+            self.next_block += 1;
+
+            if let Block::Image(
+                image_block @ ImageBlock {
+                    content: ImageContent::Image(_),
+                    ..
+                },
+            ) = block
+            {
+                return Some(self.draw(block_idx, image_block));
+            }
+        }
+
+        None
+    }
+}
+
+/// Composites every image frame of `gif`
+pub fn composite(gif: &Gif) -> Result<Vec<CompositedFrame>, DecodeError> {
+    Compositor::new(gif).collect()
+}
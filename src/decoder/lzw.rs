@@ -1,68 +1,201 @@
-use crate::gif::{Frame, ImageData, SubBlock, descriptor::ImageDescriptor, table::ColorTable};
+use crate::gif::{ImageData, SubBlock};
 
 use super::DecodeError;
 
-pub fn lzw_decode(
-    descriptor: &ImageDescriptor,
-    data: &ImageData,
-    color_table: &ColorTable,
-) -> Result<Frame, DecodeError> {
-    let mut code_size = data.lzw_min_code_size as u16;
+/// Codes are at most 12 bits wide
+pub(crate) const MAX_CODE_SIZE: u16 = 12;
+pub(crate) const MAX_CODES: usize = 1 << MAX_CODE_SIZE;
+
+/// Decompresses image data into `pixel_count` color table indices.
+///
+/// Missing pixels of a truncated stream are filled with index 0.
+pub fn lzw_decode(data: &ImageData, pixel_count: usize) -> Result<Vec<u8>, DecodeError> {
+    let min_code_size = data.lzw_min_code_size as u16;
+    if !(1..MAX_CODE_SIZE).contains(&min_code_size) {
+        return Err(DecodeError::InvalidData(
+            format!("invalid LZW minimum code size: {min_code_size}").into(),
+        ));
+    }
+
+    let clear_code = 1u16 << min_code_size;
+    let end_code = clear_code + 1;
+
+    let mut table = CodeTable::new(min_code_size);
+    let mut code_size = min_code_size;
     let mut code_stream = CodeStream::new(data);
-    let first_code = code_stream.next_code(code_size);
-    todo!()
+    let mut prev_code: Option<u16> = None;
+    let mut indices = Vec::with_capacity(pixel_count);
+
+    while indices.len() < pixel_count {
+        let Some(code) = code_stream.next_code(code_size) else {
+            break;
+        };
+
+        if code == clear_code {
+            table.reset();
+            code_size = min_code_size;
+            prev_code = None;
+            continue;
+        }
+
+        if code == end_code {
+            break;
+        }
+
+        match prev_code {
+            None if code > clear_code => {
+                return Err(DecodeError::InvalidData(
+                    format!("first LZW code `{code}` is not a root code").into(),
+                ));
+            }
+            None => indices.push(code as u8),
+            Some(prev) => {
+                let next_code = table.len();
+                let first = match code {
+                    _ if code < next_code => table.first(code),
+                    _ if code == next_code => table.first(prev),
+                    _ => {
+                        return Err(DecodeError::InvalidData(
+                            format!("LZW code `{code}` is not in the code table").into(),
+                        ));
+                    }
+                };
+
+                if !table.is_full() {
+                    table.push(prev, first);
+                }
+                table.write(code, &mut indices);
+            }
+        }
+
+        // the code size grows once the next free code no longer fits
+        if table.len() >= 1 << (code_size + 1) && code_size + 1 < MAX_CODE_SIZE {
+            code_size += 1;
+        }
+
+        prev_code = Some(code);
+    }
+
+    indices.resize(pixel_count, 0);
+
+    Ok(indices)
+}
+
+/// Code table where every string is stored as a prefix code plus a suffix
+struct CodeTable {
+    prefix: Vec<u16>,
+    suffix: Vec<u8>,
+    first: Vec<u8>,
+    len: Vec<u16>,
+    reserved: usize,
+    buf: Vec<u8>,
+}
+
+impl CodeTable {
+    fn new(min_code_size: u16) -> Self {
+        let roots = 1usize << min_code_size;
+        // roots, clear code and end of information code
+        let reserved = roots + 2;
+
+        let mut table = Self {
+            prefix: Vec::with_capacity(MAX_CODES),
+            suffix: Vec::with_capacity(MAX_CODES),
+            first: Vec::with_capacity(MAX_CODES),
+            len: Vec::with_capacity(MAX_CODES),
+            reserved,
+            buf: Vec::new(),
+        };
+
+        for code in 0..reserved {
+            table.prefix.push(0);
+            table.suffix.push(code as u8);
+            table.first.push(code as u8);
+            table.len.push(1);
+        }
+
+        table
+    }
+
+    fn reset(&mut self) {
+        self.prefix.truncate(self.reserved);
+        self.suffix.truncate(self.reserved);
+        self.first.truncate(self.reserved);
+        self.len.truncate(self.reserved);
+    }
+
+    fn len(&self) -> u16 {
+        self.suffix.len() as u16
+    }
+
+    fn is_full(&self) -> bool {
+        self.suffix.len() >= MAX_CODES
+    }
+
+    fn first(&self, code: u16) -> u8 {
+        self.first[code as usize]
+    }
+
+    fn push(&mut self, prefix: u16, suffix: u8) {
+        let prefix_idx = prefix as usize;
+        self.first.push(self.first[prefix_idx]);
+        self.len.push(self.len[prefix_idx] + 1);
+        self.prefix.push(prefix);
+        self.suffix.push(suffix);
+    }
+
+    /// Appends the string of `code` to `out`
+    fn write(&mut self, code: u16, out: &mut Vec<u8>) {
+        self.buf.clear();
+
+        let mut code = code as usize;
+        for _ in 0..self.len[code] {
+            self.buf.push(self.suffix[code]);
+            code = self.prefix[code] as usize;
+        }
+
+        out.extend(self.buf.iter().rev());
+    }
 }
 
 /// Transforms sub blocks into a continuous stream of bytes
 struct ByteStream<'a> {
-    source: &'a Vec<SubBlock>,
+    source: &'a [SubBlock],
     source_idx: usize,
     block_idx: usize,
 }
 
 impl<'a> ByteStream<'a> {
-    fn new(blocks: &'a Vec<SubBlock>) -> ByteStream<'a> {
-        assert!(!blocks.is_empty());
+    fn new(blocks: &'a [SubBlock]) -> ByteStream<'a> {
         Self {
             source: blocks,
             block_idx: 0,
             source_idx: 0,
         }
     }
-
-    fn at_end(&self) -> bool {
-        if self.source_idx < self.source.len() - 1 {
-            return false;
-        }
-
-        let block = &self.source[self.source_idx];
-
-        self.block_idx < block.0.len()
-    }
 }
 
 impl Iterator for ByteStream<'_> {
     type Item = u8;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.at_end() {
-            return None;
-        }
-
-        self.block_idx += 1;
+        loop {
+            let block = self.source.get(self.source_idx)?;
 
-        let block = &self.source[self.source_idx];
+            if let Some(byte) = block.0.get(self.block_idx) {
+                self.block_idx += 1;
+                return Some(*byte);
+            }
 
-        if let Some(byte) = block.0.get(self.block_idx) {
-            return Some(*byte);
-        } else {
             self.source_idx += 1;
-            self.next()
+            self.block_idx = 0;
         }
     }
 }
 
 /// Extracts LZW codes from bytes
+///
+/// Codes are packed starting from the least significant bit of each byte, so
+/// bytes are bit reversed before they enter the MSB first [`BitBuffer`].
 struct CodeStream<'a> {
     bbuf: BitBuffer,
     bytes: ByteStream<'a>,
@@ -76,10 +209,14 @@ impl<'a> CodeStream<'a> {
         }
     }
 
-    fn next_code(&mut self, code_size: u16) -> u16 {
-        assert!(code_size <= 4095);
-        self.bbuf.fill(&mut self.bytes);
-        self.bbuf.pop_front(code_size).expect("code")
+    /// Reads a code of `code_size + 1` bits
+    fn next_code(&mut self, code_size: u16) -> Option<u16> {
+        assert!(code_size < MAX_CODE_SIZE);
+        self.bbuf
+            .fill(&mut self.bytes.by_ref().map(u8::reverse_bits));
+        let reversed = self.bbuf.pop_front(code_size)?;
+
+        Some(reversed.reverse_bits() >> (16 - (code_size + 1)))
     }
 }
 
@@ -116,7 +253,7 @@ impl BitBuffer {
     }
 
     fn has_space_left(&self) -> bool {
-        self.space_left() > 0
+        self.space_left() >= 8
     }
 
     // 1111 0000
@@ -135,13 +272,12 @@ impl BitBuffer {
         let rotated = self.buf.rotate_left(code_size as u32 + 1);
 
         let desired_mask = (1u16 << (code_size + 1)) - 1;
-        let rest_mask = desired_mask.reverse_bits();
 
         // take first code_size bits
         let desired = rotated & desired_mask as u64;
         // remove the popped bits
 
-        let rotated_and_removed = rotated & rest_mask as u64;
+        let rotated_and_removed = rotated & !(desired_mask as u64);
 
         self.buf = rotated_and_removed;
         self.n_bits -= (code_size + 1) as u8;
@@ -200,6 +336,46 @@ mod should {
         assert_eq!(buf.buf, 0xFF_FF_FF_FF_FF_FF_FF_00)
     }
 
+    // LZW
+
+    #[test]
+    fn decode_sample_image() {
+        let data = ImageData {
+            lzw_min_code_size: 2,
+            sub_blocks: vec![SubBlock(vec![
+                0x8C, 0x2D, 0x99, 0x87, 0x2A, 0x1C, 0xDC, 0x33, 0xA0, 0x02, 0x75, 0xEC, 0x95, 0xFA,
+                0xA8, 0xDE, 0x60, 0x8C, 0x04, 0x91, 0x4C, 0x01,
+            ])],
+        };
+
+        let indices = lzw_decode(&data, 100).unwrap();
+
+        let expected_rows: [&[u8]; 10] = [
+            &[1, 1, 1, 1, 1, 2, 2, 2, 2, 2],
+            &[1, 1, 1, 1, 1, 2, 2, 2, 2, 2],
+            &[1, 1, 1, 1, 1, 2, 2, 2, 2, 2],
+            &[1, 1, 1, 0, 0, 0, 0, 2, 2, 2],
+            &[1, 1, 1, 0, 0, 0, 0, 2, 2, 2],
+            &[2, 2, 2, 0, 0, 0, 0, 1, 1, 1],
+            &[2, 2, 2, 0, 0, 0, 0, 1, 1, 1],
+            &[2, 2, 2, 2, 2, 1, 1, 1, 1, 1],
+            &[2, 2, 2, 2, 2, 1, 1, 1, 1, 1],
+            &[2, 2, 2, 2, 2, 1, 1, 1, 1, 1],
+        ];
+        assert_eq!(indices, expected_rows.concat());
+    }
+
+    #[test]
+    fn reject_unknown_code() {
+        // clear code followed by code 7 with a 3 bit code size
+        let data = ImageData {
+            lzw_min_code_size: 2,
+            sub_blocks: vec![SubBlock(vec![0b0011_1100])],
+        };
+
+        assert!(lzw_decode(&data, 4).is_err());
+    }
+
     #[test]
     fn return_none_when_empty() {
         let mut buf = BitBuffer::default();
@@ -1,9 +1,12 @@
 use std::borrow::Cow;
 use std::fmt::Debug;
 use std::io::Read;
+use std::time::Duration;
 
 use block::blocks;
-use lzw::lzw_decode;
+pub use composite::{CompositedFrame, Compositor, TRANSPARENT, composite};
+pub use lzw::lzw_decode;
+pub(crate) use lzw::{MAX_CODE_SIZE, MAX_CODES};
 use nom::Parser;
 use nom::bytes::{tag, take};
 use nom::combinator::{all_consuming, cond, map};
@@ -13,6 +16,7 @@ use thiserror::Error;
 
 mod block;
 mod common;
+mod composite;
 mod gct;
 mod lsd;
 mod lzw;
@@ -20,7 +24,7 @@ mod lzw;
 use gct::global_color_table;
 use lsd::logical_screen_descriptor;
 
-use crate::gif::{Block, Frame, Gif, GifVersion, ImageBlock, ImageContent};
+use crate::gif::{Block, Color, Frame, Gif, GifVersion, Image, ImageBlock, ImageContent};
 
 #[derive(Debug, Error)]
 pub enum DecodeError {
@@ -79,7 +83,7 @@ impl TryFrom<VersionBytes<'_>> for GifVersion {
 #[derive(Debug)]
 struct VersionBytes<'a>(&'a [u8]);
 
-fn header(input: &[u8]) -> IResult<&[u8], VersionBytes> {
+fn header(input: &[u8]) -> IResult<&[u8], VersionBytes<'_>> {
     map(preceded(tag("GIF"), take(3usize)), VersionBytes).parse(input)
 }
 
@@ -87,6 +91,37 @@ pub fn decode(gif: Gif) -> Result<Vec<Frame>, DecodeError> {
     GifDecoder::create(gif).decode()
 }
 
+/// Decompresses the color table indices of `image` in row-major order
+pub fn decode_indices(image: &Image) -> Result<Vec<u8>, DecodeError> {
+    let width = image.descriptor.width as usize;
+    let height = image.descriptor.height as usize;
+    let indices = lzw_decode(&image.data, width * height)?;
+
+    if image.descriptor.is_interlaced() {
+        Ok(deinterlace(&indices, width, height))
+    } else {
+        Ok(indices)
+    }
+}
+
+/// Interlaced images store every 8th row starting from 0, then every 8th
+/// from 4, every 4th from 2 and finally every 2nd row from 1
+fn deinterlace(indices: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let mut deinterlaced = vec![0; indices.len()];
+    let rows = (0..height)
+        .step_by(8)
+        .chain((4..height).step_by(8))
+        .chain((2..height).step_by(4))
+        .chain((1..height).step_by(2));
+
+    for (src_row, dst_row) in rows.enumerate() {
+        let src = &indices[src_row * width..(src_row + 1) * width];
+        deinterlaced[dst_row * width..(dst_row + 1) * width].copy_from_slice(src);
+    }
+
+    deinterlaced
+}
+
 #[derive(Clone)]
 struct GifDecoder {
     gif: Gif,
@@ -102,8 +137,11 @@ impl GifDecoder {
     }
 
     fn decode(mut self) -> Result<Vec<Frame>, DecodeError> {
-        for block in &self.gif.blocks {
-            if let Some(frame) = self.decode_frame(block)? {
+        let mut start = Duration::ZERO;
+
+        for (block_idx, block) in self.gif.blocks.iter().enumerate() {
+            if let Some(frame) = self.decode_frame(block_idx, block, start)? {
+                start += frame.delay;
                 self.decoded_frames.push(frame);
             };
         }
@@ -111,25 +149,103 @@ impl GifDecoder {
         Ok(self.decoded_frames)
     }
 
-    fn decode_frame(&self, block: &Block) -> Result<Option<Frame>, DecodeError> {
+    fn decode_frame(
+        &self,
+        block_idx: usize,
+        block: &Block,
+        start: Duration,
+    ) -> Result<Option<Frame>, DecodeError> {
         match block {
             Block::CommentExtension(_) | Block::ApplicationExtension(_) => Ok(None),
-            Block::Image(image_block) => self.decode_image_block(image_block),
+            Block::Image(image_block) => self.decode_image_block(block_idx, image_block, start),
         }
     }
 
-    fn decode_image_block(&self, block: &ImageBlock) -> Result<Option<Frame>, DecodeError> {
+    fn decode_image_block(
+        &self,
+        block_idx: usize,
+        block: &ImageBlock,
+        start: Duration,
+    ) -> Result<Option<Frame>, DecodeError> {
         match &block.content {
             ImageContent::PlainText(_) => Ok(None),
             ImageContent::Image(image) => {
-                let color_table = image
-                    .lct
-                    .as_ref()
-                    .or(self.gif.gct.as_ref())
-                    .expect("one color table should be defined");
-
-                lzw_decode(&image.descriptor, &image.data, color_table).map(Some)
+                let color_table = image.lct.as_ref().or(self.gif.gct.as_ref()).ok_or(
+                    DecodeError::InvalidData("image has no color table defined".into()),
+                )?;
+
+                let indices = decode_indices(image)?;
+                let pixels = indices
+                    .iter()
+                    .map(|idx| {
+                        color_table
+                            .get(*idx)
+                            .copied()
+                            .unwrap_or(Color::from_triple((0, 0, 0)))
+                    })
+                    .collect();
+
+                let gce = block.gce.as_ref();
+                let descriptor = &image.descriptor;
+
+                Ok(Some(Frame {
+                    pixels,
+                    indices,
+                    left: descriptor.left,
+                    top: descriptor.top,
+                    width: descriptor.width,
+                    height: descriptor.height,
+                    delay: gce.map(|gce| gce.delay()).unwrap_or_default(),
+                    disposal: gce.map(|gce| gce.disposal_method()).unwrap_or_default(),
+                    transparent_color_idx: gce.and_then(|gce| gce.transparent_color_idx()),
+                    block_idx,
+                    start,
+                }))
             }
         }
     }
 }
+
+#[cfg(test)]
+mod should {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::gif::extension::DisposalMethod;
+
+    const SAMPLE: &[u8] = &[
+        0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x0A, 0x00, 0x0A, 0x00, 0x91, 0x00, 0x00, 0xFF, 0xFF,
+        0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x00, 0x21, 0xF9, 0x04, 0x09, 0x0A,
+        0x00, 0x03, 0x00, 0x2C, 0x01, 0x00, 0x02, 0x00, 0x0A, 0x00, 0x0A, 0x00, 0x00, 0x02, 0x16,
+        0x8C, 0x2D, 0x99, 0x87, 0x2A, 0x1C, 0xDC, 0x33, 0xA0, 0x02, 0x75, 0xEC, 0x95, 0xFA, 0xA8,
+        0xDE, 0x60, 0x8C, 0x04, 0x91, 0x4C, 0x01, 0x00, 0x3B,
+    ];
+
+    #[test]
+    fn decode_frame_metadata() {
+        let gif = parse(SAMPLE).unwrap();
+        let frames = decode(gif).unwrap();
+
+        assert_eq!(frames.len(), 1);
+        let frame = &frames[0];
+        assert_eq!((frame.left, frame.top), (1, 2));
+        assert_eq!((frame.width, frame.height), (10, 10));
+        assert_eq!(frame.delay, Duration::from_millis(100));
+        assert_eq!(frame.disposal, DisposalMethod::RestoreBackground);
+        assert_eq!(frame.transparent_color_idx, Some(3));
+        assert_eq!(frame.block_idx, 0);
+        assert_eq!(frame.start, Duration::ZERO);
+        assert_eq!(frame.pixels[0], Color::from_triple((0xFF, 0x00, 0x00)));
+        assert!(!frame.is_transparent(0));
+    }
+
+    #[test]
+    fn deinterlace_rows() {
+        let interlaced: Vec<u8> = [0, 8, 4, 2, 6, 1, 3, 5, 7, 9].to_vec();
+
+        assert_eq!(
+            deinterlace(&interlaced, 1, 10),
+            (0..10).collect::<Vec<u8>>()
+        );
+    }
+}
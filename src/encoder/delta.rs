@@ -0,0 +1,157 @@
+use std::collections::HashSet;
+
+use crate::gif::{Color, Rect, extension::DisposalMethod, table::MAX_COLORS};
+
+/// Area written for frames that change nothing, images can not be empty
+const UNCHANGED: Rect = Rect {
+    left: 0,
+    top: 0,
+    width: 1,
+    height: 1,
+};
+
+/// Tracks what the viewer shows so that only changed pixels have to be
+/// written.
+///
+/// A frame is held back until the next one is known: when the next frame
+/// turns pixels transparent, the held frame has to cover those pixels and
+/// clear them with its disposal.
+pub(crate) struct DeltaCanvas<T> {
+    width: usize,
+    height: usize,
+    /// What is shown before the pending frame is drawn, `None` where the
+    /// canvas is transparent
+    shown: Vec<Option<Color>>,
+    pending: Option<Pending<T>>,
+}
+
+struct Pending<T> {
+    target: Vec<Option<Color>>,
+    /// Changed area, `None` if nothing changed
+    rect: Option<Rect>,
+    data: T,
+}
+
+/// Part of a frame that has to be written
+pub(crate) struct Delta<T> {
+    pub(crate) rect: Rect,
+    /// Colors of `rect`, `None` where the pixel is transparent or unchanged
+    pub(crate) pixels: Vec<Option<Color>>,
+    pub(crate) disposal: DisposalMethod,
+    pub(crate) data: T,
+}
+
+impl<T> DeltaCanvas<T> {
+    pub(crate) fn new(width: u16, height: u16) -> Self {
+        let (width, height) = (width as usize, height as usize);
+
+        Self {
+            width,
+            height,
+            shown: vec![None; width * height],
+            pending: None,
+        }
+    }
+
+    /// Adds the next frame and returns the previous one once it can be
+    /// written. `target` holds the color every pixel should show.
+    pub(crate) fn push(&mut self, target: Vec<Option<Color>>, data: T) -> Option<Delta<T>> {
+        let previous = self.pending.take().map(|pending| {
+            // pixels can only become transparent by clearing the frame before
+            let needs_clear =
+                self.bounds(|idx| target[idx].is_none() && pending.target[idx].is_some());
+
+            match needs_clear {
+                Some(clear) => {
+                    let rect = pending.rect.map_or(clear, |rect| rect.union(&clear));
+                    self.finalize(pending, rect, DisposalMethod::RestoreBackground)
+                }
+                None => {
+                    let rect = pending.rect.unwrap_or(UNCHANGED);
+                    self.finalize(pending, rect, DisposalMethod::Keep)
+                }
+            }
+        });
+
+        // the first frame covers the whole canvas so no viewer has to guess a
+        // background
+        let rect = match previous {
+            None => Some(Rect::new(0, 0, self.width as u16, self.height as u16)),
+            Some(_) => self.bounds(|idx| target[idx] != self.shown[idx]),
+        };
+
+        self.pending = Some(Pending { target, rect, data });
+
+        previous
+    }
+
+    /// Returns the last frame
+    pub(crate) fn finish(mut self) -> Option<Delta<T>> {
+        self.pending.take().map(|pending| {
+            let rect = pending.rect.unwrap_or(UNCHANGED);
+            self.finalize(pending, rect, DisposalMethod::Keep)
+        })
+    }
+
+    fn finalize(&mut self, pending: Pending<T>, rect: Rect, disposal: DisposalMethod) -> Delta<T> {
+        let indices: Vec<usize> = (rect.top as usize..rect.bottom() as usize)
+            .flat_map(|y| (rect.left as usize..rect.right() as usize).map(move |x| (x, y)))
+            .map(|(x, y)| y * self.width + x)
+            .collect();
+        let mut pixels: Vec<Option<Color>> = indices
+            .iter()
+            .map(|idx| pending.target[*idx].filter(|_| pending.target[*idx] != self.shown[*idx]))
+            .collect();
+
+        // a full table leaves no entry for unchanged pixels, so they are drawn
+        // again when the frame shows no transparency
+        let colors: HashSet<&Color> = pixels.iter().flatten().collect();
+        let is_opaque = indices.iter().all(|idx| pending.target[*idx].is_some());
+        if colors.len() >= MAX_COLORS && is_opaque {
+            pixels = indices.iter().map(|idx| pending.target[*idx]).collect();
+        }
+
+        // pixels outside of the rectangle already show the target
+        self.shown = pending.target;
+
+        if disposal == DisposalMethod::RestoreBackground {
+            for y in rect.top as usize..rect.bottom() as usize {
+                let row = y * self.width;
+                self.shown[row + rect.left as usize..row + rect.right() as usize].fill(None);
+            }
+        }
+
+        Delta {
+            rect,
+            pixels,
+            disposal,
+            data: pending.data,
+        }
+    }
+
+    /// Bounding box of the pixels matching `predicate`
+    fn bounds<P: Fn(usize) -> bool>(&self, predicate: P) -> Option<Rect> {
+        let (mut left, mut top) = (usize::MAX, usize::MAX);
+        let (mut right, mut bottom) = (0, 0);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if predicate(y * self.width + x) {
+                    left = left.min(x);
+                    top = top.min(y);
+                    right = right.max(x + 1);
+                    bottom = bottom.max(y + 1);
+                }
+            }
+        }
+
+        (left < right).then(|| {
+            Rect::new(
+                left as u16,
+                top as u16,
+                (right - left) as u16,
+                (bottom - top) as u16,
+            )
+        })
+    }
+}
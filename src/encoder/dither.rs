@@ -0,0 +1,354 @@
+//! Spreads the error of mapping truecolor pixels onto a palette to hide banding
+
+use super::palette::Palette;
+use super::{Rgba, is_transparent};
+use crate::gif::{Color, table::ColorTable};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dither {
+    /// Every pixel becomes its nearest palette color
+    #[default]
+    None,
+    /// Error diffusion to 4 neighbours
+    FloydSteinberg,
+    /// Error diffusion of 3/4 of the error, keeps contrast in flat areas
+    Atkinson,
+    /// Error diffusion over 3 rows, smoother than Floyd–Steinberg
+    Sierra,
+    /// Ordered dithering with an 8x8 Bayer matrix, compresses better than
+    /// error diffusion
+    Bayer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DitherOptions {
+    pub method: Dither,
+    /// 0.0 to 1.0, how much of the error is spread
+    pub strength: f32,
+    /// Pixels that did not change since the previous frame keep the color
+    /// they were shown with, so dither noise does not shimmer between frames
+    pub stable_static_areas: bool,
+}
+
+impl Default for DitherOptions {
+    fn default() -> Self {
+        Self {
+            method: Dither::default(),
+            strength: 1.0,
+            stable_static_areas: true,
+        }
+    }
+}
+
+/// Maps `pixels` onto `table`, transparent pixels become `transparent_idx`
+/// when it is set
+pub fn dither(
+    pixels: &[Rgba],
+    width: usize,
+    table: &ColorTable,
+    transparent_idx: Option<u8>,
+    options: DitherOptions,
+) -> Vec<u8> {
+    let mut palette = Palette::with_transparent_idx(table.colors().to_vec(), transparent_idx);
+
+    map_pixels(pixels, width, &mut palette, options, None)
+}
+
+/// Previous frame and the colors it was shown with, `None` where it was
+/// transparent
+pub(crate) struct Shown<'a> {
+    pub(crate) pixels: &'a [Rgba],
+    pub(crate) colors: &'a [Option<Color>],
+}
+
+pub(crate) fn map_pixels(
+    pixels: &[Rgba],
+    width: usize,
+    palette: &mut Palette,
+    options: DitherOptions,
+    previous: Option<Shown>,
+) -> Vec<u8> {
+    let strength = options.strength.clamp(0.0, 1.0);
+    let previous =
+        previous.filter(|_| options.stable_static_areas && options.method != Dither::None);
+    let is_static = |idx: usize| {
+        previous
+            .as_ref()
+            .is_some_and(|previous| previous.pixels[idx] == pixels[idx])
+    };
+
+    match kernel(options.method) {
+        _ if strength == 0.0 || width == 0 => (0..pixels.len())
+            .map(|idx| match is_static(idx) {
+                true => palette.index_of(shown(&previous, idx, pixels[idx])),
+                false => palette.index_of(pixels[idx]),
+            })
+            .collect(),
+        Some((weights, divisor)) => {
+            let mut diffusion = ErrorDiffusion::new(width, weights, divisor * (1.0 / strength));
+
+            (0..pixels.len())
+                .map(|idx| {
+                    let pixel = pixels[idx];
+
+                    if is_transparent(pixel) || is_static(idx) {
+                        // error is not carried into or out of skipped pixels
+                        diffusion.skip(idx);
+                        return palette.index_of(shown(&previous, idx, pixel));
+                    }
+
+                    let target = diffusion.apply(idx, pixel);
+                    let mapped = palette.index_of(target);
+                    diffusion.spread(idx, target, palette.colors()[mapped as usize]);
+
+                    mapped
+                })
+                .collect()
+        }
+        None => {
+            let spread = strength * 255.0 / (palette.colors().len() as f32).cbrt();
+
+            (0..pixels.len())
+                .map(|idx| {
+                    let pixel = pixels[idx];
+
+                    if is_transparent(pixel) || is_static(idx) {
+                        return palette.index_of(shown(&previous, idx, pixel));
+                    }
+
+                    let (x, y) = (idx % width, idx / width);
+                    let threshold = (BAYER[y % 8][x % 8] as f32 + 0.5) / 64.0 - 0.5;
+                    let offset = threshold * spread;
+                    let [r, g, b, a] = pixel;
+                    let shifted = [r, g, b].map(|channel| clamp(channel as f32 + offset));
+
+                    palette.index_of([shifted[0], shifted[1], shifted[2], a])
+                })
+                .collect()
+        }
+    }
+}
+
+/// Color the pixel at `idx` was shown with if it is static, otherwise `pixel`
+fn shown(previous: &Option<Shown>, idx: usize, pixel: Rgba) -> Rgba {
+    match previous {
+        Some(previous) if previous.pixels[idx] == pixel => match previous.colors[idx] {
+            Some(color) => [color.r, color.g, color.b, pixel[3]],
+            None => pixel,
+        },
+        _ => pixel,
+    }
+}
+
+/// Neighbour offsets with their share of the error and the total of shares
+type Kernel = (&'static [(isize, usize, f32)], f32);
+
+fn kernel(method: Dither) -> Option<Kernel> {
+    const NONE: &[(isize, usize, f32)] = &[];
+    const FLOYD_STEINBERG: &[(isize, usize, f32)] =
+        &[(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)];
+    const ATKINSON: &[(isize, usize, f32)] = &[
+        (1, 0, 1.0),
+        (2, 0, 1.0),
+        (-1, 1, 1.0),
+        (0, 1, 1.0),
+        (1, 1, 1.0),
+        (0, 2, 1.0),
+    ];
+    const SIERRA: &[(isize, usize, f32)] = &[
+        (1, 0, 5.0),
+        (2, 0, 3.0),
+        (-2, 1, 2.0),
+        (-1, 1, 4.0),
+        (0, 1, 5.0),
+        (1, 1, 4.0),
+        (2, 1, 2.0),
+        (-1, 2, 2.0),
+        (0, 2, 3.0),
+        (1, 2, 2.0),
+    ];
+
+    match method {
+        Dither::None => Some((NONE, 1.0)),
+        Dither::FloydSteinberg => Some((FLOYD_STEINBERG, 16.0)),
+        Dither::Atkinson => Some((ATKINSON, 8.0)),
+        Dither::Sierra => Some((SIERRA, 32.0)),
+        Dither::Bayer => None,
+    }
+}
+
+const BAYER: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// Accumulated error of the next 3 rows, the row of the current pixel first
+struct ErrorDiffusion {
+    width: usize,
+    weights: &'static [(isize, usize, f32)],
+    divisor: f32,
+    rows: [Vec<[f32; 3]>; 3],
+    row: usize,
+}
+
+impl ErrorDiffusion {
+    fn new(width: usize, weights: &'static [(isize, usize, f32)], divisor: f32) -> Self {
+        Self {
+            width,
+            weights,
+            divisor,
+            rows: std::array::from_fn(|_| vec![[0.0; 3]; width]),
+            row: 0,
+        }
+    }
+
+    fn advance_to(&mut self, idx: usize) {
+        while self.row < idx / self.width {
+            self.rows.rotate_left(1);
+            self.rows[2].fill([0.0; 3]);
+            self.row += 1;
+        }
+    }
+
+    fn skip(&mut self, idx: usize) {
+        self.advance_to(idx);
+        self.rows[0][idx % self.width] = [0.0; 3];
+    }
+
+    /// Pixel with the error spread to it so far
+    fn apply(&mut self, idx: usize, [r, g, b, a]: Rgba) -> Rgba {
+        self.advance_to(idx);
+        let [er, eg, eb] = self.rows[0][idx % self.width];
+
+        [
+            clamp(r as f32 + er),
+            clamp(g as f32 + eg),
+            clamp(b as f32 + eb),
+            a,
+        ]
+    }
+
+    fn spread(&mut self, idx: usize, [r, g, b, _]: Rgba, mapped: Color) {
+        let error = [
+            r as f32 - mapped.r as f32,
+            g as f32 - mapped.g as f32,
+            b as f32 - mapped.b as f32,
+        ];
+        let x = idx % self.width;
+
+        for (dx, dy, weight) in self.weights {
+            let Some(nx) = x.checked_add_signed(*dx).filter(|nx| *nx < self.width) else {
+                continue;
+            };
+
+            let target = &mut self.rows[*dy][nx];
+            for channel in 0..3 {
+                target[channel] += error[channel] * weight / self.divisor;
+            }
+        }
+    }
+}
+
+fn clamp(value: f32) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+
+    fn black_and_white() -> ColorTable {
+        ColorTable::new(vec![
+            Color::from_triple((0, 0, 0)),
+            Color::from_triple((255, 255, 255)),
+        ])
+    }
+
+    fn mean_of(indices: &[u8]) -> f32 {
+        indices.iter().map(|idx| *idx as f32).sum::<f32>() / indices.len() as f32
+    }
+
+    #[test]
+    fn approximate_gray_with_every_method() {
+        let gray = vec![[128, 128, 128, 255]; 16 * 16];
+
+        let plain = dither(
+            &gray,
+            16,
+            &black_and_white(),
+            None,
+            DitherOptions::default(),
+        );
+        assert!(plain.iter().all(|idx| *idx == plain[0]));
+
+        for method in [
+            Dither::FloydSteinberg,
+            Dither::Atkinson,
+            Dither::Sierra,
+            Dither::Bayer,
+        ] {
+            let options = DitherOptions {
+                method,
+                ..Default::default()
+            };
+            let indices = dither(&gray, 16, &black_and_white(), None, options);
+
+            // roughly half of the pixels turn white
+            let white = mean_of(&indices);
+            assert!((0.3..0.7).contains(&white), "{method:?}: {white}");
+        }
+    }
+
+    #[test]
+    fn scale_with_strength() {
+        let gray = vec![[100, 100, 100, 255]; 16 * 16];
+        let options = DitherOptions {
+            method: Dither::FloydSteinberg,
+            strength: 0.0,
+            ..Default::default()
+        };
+
+        let indices = dither(&gray, 16, &black_and_white(), None, options);
+
+        assert!(indices.iter().all(|idx| *idx == 0));
+    }
+
+    #[test]
+    fn keep_static_pixels_as_shown_before() {
+        let table = black_and_white();
+        let mut palette = Palette::with_transparent_idx(table.colors().to_vec(), None);
+        let pixels = vec![[128, 128, 128, 255]; 8];
+        let colors = [Some(table.colors()[1]); 8];
+        let previous = Shown {
+            pixels: &pixels,
+            colors: &colors,
+        };
+        let options = DitherOptions {
+            method: Dither::FloydSteinberg,
+            ..Default::default()
+        };
+
+        let indices = map_pixels(&pixels, 4, &mut palette, options, Some(previous));
+
+        assert_eq!(indices, vec![1; 8]);
+    }
+
+    #[test]
+    fn map_transparent_pixels_to_transparent_idx() {
+        let pixels = [[0, 0, 0, 0], [250, 250, 250, 255]];
+        let options = DitherOptions {
+            method: Dither::Sierra,
+            ..Default::default()
+        };
+
+        let indices = dither(&pixels, 2, &black_and_white(), Some(0), options);
+
+        assert_eq!(indices, vec![0, 1]);
+    }
+}
@@ -0,0 +1,265 @@
+use super::{EncodeError, Encoder, Rgba, RgbaFrame, quantize::QuantizeOptions};
+use crate::gif::{Gif, timeline::merged_delays};
+
+/// Fewer colors per palette, the first way to save bytes
+const COLOR_STEPS: [usize; 3] = [128, 64, 32];
+/// Error budgets of the lossy compression, tried after the colors
+const LOSSY_STEPS: [u16; 3] = [20, 40, 80];
+/// Keeping every n-th frame, tried after the lossy compression
+const FRAME_STEPS: [usize; 3] = [2, 3, 4];
+/// Canvas scales as fractions, the last resort
+const SCALE_STEPS: [(u32, u32); 4] = [(3, 4), (1, 2), (3, 8), (1, 4)];
+
+/// Settings [`Encoder::encode_to_fit`] settled on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FitSettings {
+    /// Opaque colors per palette at most
+    pub max_colors: usize,
+    /// Error budget of the lossy compression, 0 if lossless
+    pub lossy: u16,
+    /// Every n-th frame is kept, dropped frames add their delay to the one
+    /// before
+    pub frame_step: usize,
+    pub width: u16,
+    pub height: u16,
+    /// Size of the encoded GIF in bytes
+    pub len: usize,
+}
+
+impl Encoder {
+    /// Encodes the frames into at most `max_bytes`, lowering the quality step
+    /// by step until the GIF fits.
+    ///
+    /// Starting from the encoder's own settings it tries fewer colors, then
+    /// stronger lossy compression, then dropping frames and finally a smaller
+    /// canvas, each step keeping the previous ones. Fails if even the
+    /// smallest setting does not fit.
+    pub fn encode_to_fit(&self, max_bytes: usize) -> Result<(Gif, FitSettings), EncodeError> {
+        let mut smallest = usize::MAX;
+
+        for mut settings in self.ladder() {
+            let gif = self.with_settings(&settings).encode()?;
+            settings.len = gif.encoded_len()?;
+
+            if settings.len <= max_bytes {
+                return Ok((gif, settings));
+            }
+            smallest = smallest.min(settings.len);
+        }
+
+        Err(EncodeError::InvalidData(
+            format!("could not fit into {max_bytes} bytes, the smallest attempt took {smallest}")
+                .into(),
+        ))
+    }
+
+    /// Settings from the highest to the lowest quality
+    fn ladder(&self) -> Vec<FitSettings> {
+        let mut settings = FitSettings {
+            max_colors: self.quantize.max_colors,
+            lossy: self.lossy,
+            frame_step: 1,
+            width: self.width,
+            height: self.height,
+            len: 0,
+        };
+        let mut ladder = vec![settings];
+
+        let (max_colors, lossy) = (settings.max_colors, settings.lossy);
+        for max_colors in COLOR_STEPS.into_iter().filter(|c| *c < max_colors) {
+            settings.max_colors = max_colors;
+            ladder.push(settings);
+        }
+        for lossy in LOSSY_STEPS.into_iter().filter(|l| *l > lossy) {
+            settings.lossy = lossy;
+            ladder.push(settings);
+        }
+        for frame_step in FRAME_STEPS
+            .into_iter()
+            .take_while(|s| *s < self.frames.len())
+        {
+            settings.frame_step = frame_step;
+            ladder.push(settings);
+        }
+        for (numerator, denominator) in SCALE_STEPS {
+            let scale = |len: u16| (len as u32 * numerator / denominator).max(1) as u16;
+            settings.width = scale(self.width);
+            settings.height = scale(self.height);
+            ladder.push(settings);
+        }
+
+        ladder
+    }
+
+    fn with_settings(&self, settings: &FitSettings) -> Encoder {
+        let frames = self
+            .frames
+            .chunks(settings.frame_step)
+            .flat_map(|frames| {
+                let pixels = downscale(
+                    &frames[0].pixels,
+                    (self.width, self.height),
+                    (settings.width, settings.height),
+                );
+                // a kept frame too long for one delay is shown several times
+                merged_delays(frames.iter().map(|frame| frame.delay_time))
+                    .into_iter()
+                    .map(move |delay_time| RgbaFrame {
+                        pixels: pixels.clone(),
+                        delay_time,
+                    })
+            })
+            .collect();
+
+        Encoder {
+            width: settings.width,
+            height: settings.height,
+            loop_count: self.loop_count,
+            quantize: QuantizeOptions {
+                max_colors: settings.max_colors,
+                ..self.quantize
+            },
+            dither: self.dither,
+            global_palette: self.global_palette,
+            delta_frames: self.delta_frames,
+            lossy: settings.lossy,
+            frames,
+        }
+    }
+}
+
+/// Averages the pixels covered by each target pixel, weighting colors by
+/// their alpha so transparent pixels do not darken their neighbors
+fn downscale(pixels: &[Rgba], (width, height): (u16, u16), to: (u16, u16)) -> Vec<Rgba> {
+    if (width, height) == to {
+        return pixels.to_vec();
+    }
+
+    let (width, height) = (width as usize, height as usize);
+    let (to_width, to_height) = (to.0 as usize, to.1 as usize);
+    let span = |idx: usize, len: usize, to: usize| {
+        let start = idx * len / to;
+        start..((idx + 1) * len / to).max(start + 1)
+    };
+
+    let mut scaled = Vec::with_capacity(to_width * to_height);
+    for y in 0..to_height {
+        for x in 0..to_width {
+            let mut sums = [0u64; 4];
+            let mut count = 0;
+
+            for sy in span(y, height, to_height) {
+                for sx in span(x, width, to_width) {
+                    let [r, g, b, a] = pixels[sy * width + sx];
+                    let alpha = a as u64;
+                    sums[0] += r as u64 * alpha;
+                    sums[1] += g as u64 * alpha;
+                    sums[2] += b as u64 * alpha;
+                    sums[3] += alpha;
+                    count += 1;
+                }
+            }
+
+            let color = |sum: u64| (sum / sums[3].max(1)) as u8;
+            scaled.push([
+                color(sums[0]),
+                color(sums[1]),
+                color(sums[2]),
+                (sums[3] / count) as u8,
+            ]);
+        }
+    }
+
+    scaled
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+
+    fn noisy_animation() -> Encoder {
+        let mut encoder = Encoder::new(24, 24);
+        let mut state = 0x2545_F491u32;
+
+        for frame in 0..4u32 {
+            let rgba: Vec<u8> = (0..24 * 24u32)
+                .flat_map(|idx| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    let shade = (idx % 24 * 10 + frame * 7) as u8;
+                    [shade, (state % 32) as u8 + shade / 2, 200, 255]
+                })
+                .collect();
+            encoder.add_frame(&rgba, 10).unwrap();
+        }
+
+        encoder
+    }
+
+    #[test]
+    fn keep_settings_when_the_output_fits() {
+        let encoder = noisy_animation();
+        let len = encoder.encode().unwrap().encoded_len().unwrap();
+
+        let (gif, settings) = encoder.encode_to_fit(len).unwrap();
+
+        assert_eq!(settings.len, len);
+        assert_eq!(
+            (settings.max_colors, settings.lossy, settings.frame_step),
+            (256, 0, 1)
+        );
+        assert_eq!(gif.encoded_len().unwrap(), len);
+    }
+
+    #[test]
+    fn lower_quality_until_the_output_fits() {
+        let encoder = noisy_animation();
+        let max_bytes = encoder.encode().unwrap().encoded_len().unwrap() / 4;
+
+        let (gif, settings) = encoder.encode_to_fit(max_bytes).unwrap();
+
+        assert!(settings.len <= max_bytes);
+        assert_eq!(gif.encoded_len().unwrap(), settings.len);
+        assert!(settings.max_colors < 256);
+        let frames = gif.images().count();
+        assert_eq!(frames, 4_usize.div_ceil(settings.frame_step));
+        assert_eq!(gif.duration(), std::time::Duration::from_millis(400));
+    }
+
+    #[test]
+    fn keep_the_duration_of_dropped_frames() {
+        let mut encoder = Encoder::new(1, 1);
+        for delay_time in [60_000, 60_000, 10] {
+            encoder.add_frame(&[9, 9, 9, 255], delay_time).unwrap();
+        }
+        let mut settings = encoder.ladder()[0];
+        settings.frame_step = 3;
+
+        let delays: Vec<u16> = encoder
+            .with_settings(&settings)
+            .frames
+            .iter()
+            .map(|frame| frame.delay_time)
+            .collect();
+
+        assert_eq!(delays, vec![60_000, 60_010]);
+    }
+
+    #[test]
+    fn fail_when_nothing_fits() {
+        assert!(noisy_animation().encode_to_fit(10).is_err());
+    }
+
+    #[test]
+    fn average_alpha_weighted_colors() {
+        let pixels = [
+            [255, 0, 0, 255],
+            [0, 0, 255, 0],
+            [255, 0, 0, 255],
+            [0, 0, 255, 0],
+        ];
+
+        assert_eq!(downscale(&pixels, (2, 2), (1, 1)), vec![[255, 0, 0, 127]]);
+    }
+}
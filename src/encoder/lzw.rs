@@ -0,0 +1,397 @@
+use super::palette::distance;
+use crate::decoder::{MAX_CODE_SIZE, MAX_CODES};
+use crate::gif::{Color, ImageData, SubBlock};
+
+/// Smallest LZW minimum code size that can represent `color_count` indices
+pub fn min_code_size(color_count: usize) -> u8 {
+    let bits = usize::BITS - color_count.saturating_sub(1).leading_zeros();
+
+    // GIF requires at least 2 bits, even for 1 bit images
+    bits.clamp(2, 8) as u8
+}
+
+/// Compresses color table indices into image data.
+///
+/// `min_code_size` is clamped to 2..=8, the sizes GIF encoders write. Every
+/// index must be below `2 ^ min_code_size`.
+pub fn lzw_encode(indices: &[u8], min_code_size: u8) -> ImageData {
+    encode(indices, min_code_size, None)
+}
+
+/// Compresses color table indices into image data, allowing pixels to change
+/// to a similar color when that continues a string already in the dictionary.
+///
+/// `colors` is the color table the indices point into. No pixel ends up
+/// further than `max_error` from its color in RGB space and transparent
+/// pixels are never changed. `min_code_size` is clamped like in
+/// [`lzw_encode`].
+pub fn lzw_encode_lossy(
+    indices: &[u8],
+    min_code_size: u8,
+    colors: &[Color],
+    transparent_idx: Option<u8>,
+    max_error: u16,
+) -> ImageData {
+    let lossy = Lossy {
+        colors,
+        transparent_idx,
+        max_distance: max_error as u32 * max_error as u32,
+    };
+
+    encode(indices, min_code_size, Some(&lossy))
+}
+
+/// Colors and error budget of a lossy compression
+struct Lossy<'a> {
+    colors: &'a [Color],
+    transparent_idx: Option<u8>,
+    /// Squared RGB distance a pixel may be off by
+    max_distance: u32,
+}
+
+impl Lossy<'_> {
+    /// Distance between two indices, `None` if one may not replace the other
+    fn distance(&self, idx: u8, other: u8) -> Option<u32> {
+        let is_transparent = |idx| Some(idx) == self.transparent_idx;
+        if is_transparent(idx) || is_transparent(other) {
+            return None;
+        }
+
+        let color = self.colors.get(idx as usize)?;
+        let other = self.colors.get(other as usize)?;
+        let distance = distance([color.r, color.g, color.b], *other);
+
+        (distance <= self.max_distance).then_some(distance)
+    }
+}
+
+fn encode(indices: &[u8], min_code_size: u8, lossy: Option<&Lossy>) -> ImageData {
+    // decoders accept 1 bit codes, but the encoder writes at least 2
+    let min_code_size = min_code_size.clamp(2, 8);
+    let mut encoder = LzwEncoder::new(min_code_size);
+
+    encoder.write_clear();
+
+    if let Some((first, rest)) = indices.split_first() {
+        let mut prefix = *first as u16;
+
+        for idx in rest {
+            if let Some(code) = encoder.dictionary.get(prefix, *idx) {
+                prefix = code;
+                continue;
+            }
+
+            if let Some(code) =
+                lossy.and_then(|lossy| encoder.dictionary.similar(prefix, *idx, lossy))
+            {
+                prefix = code;
+                continue;
+            }
+
+            encoder.write_data(prefix);
+            encoder.extend(prefix, *idx);
+            prefix = *idx as u16;
+        }
+
+        encoder.write_data(prefix);
+    }
+
+    encoder.write_end();
+
+    ImageData {
+        lzw_min_code_size: min_code_size,
+        sub_blocks: encoder.finish(),
+    }
+}
+
+struct LzwEncoder {
+    dictionary: Dictionary,
+    min_code_size: u16,
+    code_size: u16,
+    next_code: u16,
+    bits: BitWriter,
+}
+
+impl LzwEncoder {
+    fn new(min_code_size: u8) -> Self {
+        let min_code_size = min_code_size as u16;
+        Self {
+            dictionary: Dictionary::default(),
+            min_code_size,
+            code_size: min_code_size + 1,
+            next_code: (1 << min_code_size) + 2,
+            bits: BitWriter::default(),
+        }
+    }
+
+    fn clear_code(&self) -> u16 {
+        1 << self.min_code_size
+    }
+
+    fn write_clear(&mut self) {
+        self.bits.write(self.clear_code(), self.code_size);
+        self.dictionary.clear();
+        self.code_size = self.min_code_size + 1;
+        self.next_code = self.clear_code() + 2;
+    }
+
+    fn write_end(&mut self) {
+        self.bits.write(self.clear_code() + 1, self.code_size);
+    }
+
+    /// Writes a string code, growing the code size the same way the decoder
+    /// does after reading it
+    fn write_data(&mut self, code: u16) {
+        self.bits.write(code, self.code_size);
+
+        if self.next_code >= 1 << self.code_size && self.code_size < MAX_CODE_SIZE {
+            self.code_size += 1;
+        }
+    }
+
+    /// Adds `prefix + suffix` to the dictionary, starting over with a clear
+    /// code once the table is full
+    fn extend(&mut self, prefix: u16, suffix: u8) {
+        self.dictionary.insert(prefix, suffix, self.next_code);
+        self.next_code += 1;
+
+        if self.next_code as usize == MAX_CODES {
+            self.write_clear();
+        }
+    }
+
+    fn finish(self) -> Vec<SubBlock> {
+        self.bits.finish()
+    }
+}
+
+/// Open addressing hash map from `(prefix code, suffix index)` to a code
+struct Dictionary {
+    keys: Vec<u32>,
+    codes: Vec<u16>,
+    /// Last code added with each code as prefix, [`Dictionary::NONE`] if none
+    last_child: Vec<u16>,
+    /// Code added before each code with the same prefix
+    previous_sibling: Vec<u16>,
+    suffixes: Vec<u8>,
+}
+
+impl Dictionary {
+    // twice the maximum amount of codes keeps probe sequences short
+    const SLOTS: usize = MAX_CODES * 2;
+    const EMPTY: u32 = u32::MAX;
+    const NONE: u16 = u16::MAX;
+
+    fn slot(key: u32) -> usize {
+        (key.wrapping_mul(0x9E37_79B1) >> 19) as usize & (Dictionary::SLOTS - 1)
+    }
+
+    fn key(prefix: u16, suffix: u8) -> u32 {
+        ((prefix as u32) << 8) | suffix as u32
+    }
+
+    fn get(&self, prefix: u16, suffix: u8) -> Option<u16> {
+        let key = Dictionary::key(prefix, suffix);
+        let mut slot = Dictionary::slot(key);
+
+        loop {
+            match self.keys[slot] {
+                Dictionary::EMPTY => return None,
+                k if k == key => return Some(self.codes[slot]),
+                _ => slot = (slot + 1) & (Dictionary::SLOTS - 1),
+            }
+        }
+    }
+
+    fn insert(&mut self, prefix: u16, suffix: u8, code: u16) {
+        let key = Dictionary::key(prefix, suffix);
+        let mut slot = Dictionary::slot(key);
+
+        while self.keys[slot] != Dictionary::EMPTY {
+            slot = (slot + 1) & (Dictionary::SLOTS - 1);
+        }
+
+        self.keys[slot] = key;
+        self.codes[slot] = code;
+
+        self.previous_sibling[code as usize] = self.last_child[prefix as usize];
+        self.last_child[prefix as usize] = code;
+        self.suffixes[code as usize] = suffix;
+    }
+
+    /// Code continuing `prefix` with the suffix closest to `suffix` within
+    /// the error budget
+    fn similar(&self, prefix: u16, suffix: u8, lossy: &Lossy) -> Option<u16> {
+        let mut best: Option<(u32, u16)> = None;
+        let mut code = self.last_child[prefix as usize];
+
+        while code != Dictionary::NONE {
+            let distance = lossy.distance(suffix, self.suffixes[code as usize]);
+            if let Some(distance) = distance.filter(|d| best.is_none_or(|(best, _)| *d < best)) {
+                best = Some((distance, code));
+            }
+
+            code = self.previous_sibling[code as usize];
+        }
+
+        best.map(|(_, code)| code)
+    }
+
+    fn clear(&mut self) {
+        self.keys.fill(Dictionary::EMPTY);
+        self.last_child.fill(Dictionary::NONE);
+    }
+}
+
+impl Default for Dictionary {
+    fn default() -> Self {
+        Self {
+            keys: vec![Dictionary::EMPTY; Dictionary::SLOTS],
+            codes: vec![0; Dictionary::SLOTS],
+            last_child: vec![Dictionary::NONE; MAX_CODES],
+            previous_sibling: vec![Dictionary::NONE; MAX_CODES],
+            suffixes: vec![0; MAX_CODES],
+        }
+    }
+}
+
+/// Packs codes starting from the least significant bit and splits the bytes
+/// into sub blocks
+#[derive(Default)]
+struct BitWriter {
+    buf: u32,
+    n_bits: u8,
+    bytes: Vec<u8>,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, code_size: u16) {
+        self.buf |= (code as u32) << self.n_bits;
+        self.n_bits += code_size as u8;
+
+        while self.n_bits >= 8 {
+            self.bytes.push(self.buf as u8);
+            self.buf >>= 8;
+            self.n_bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<SubBlock> {
+        if self.n_bits > 0 {
+            self.bytes.push(self.buf as u8);
+        }
+
+        SubBlock::chunks(&self.bytes)
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::decoder::lzw_decode;
+    use crate::gif::data::MAX_SUB_BLOCK_LEN;
+
+    fn round_trip(indices: &[u8], min_code_size: u8) {
+        let data = lzw_encode(indices, min_code_size);
+
+        assert!(
+            data.sub_blocks
+                .iter()
+                .all(|block| !block.0.is_empty() && block.0.len() <= MAX_SUB_BLOCK_LEN)
+        );
+        assert_eq!(lzw_decode(&data, indices.len()).unwrap(), indices);
+    }
+
+    #[test]
+    fn encode_sample_image() {
+        let rows: [&[u8]; 4] = [
+            &[1, 1, 1, 1, 1, 2, 2, 2, 2, 2],
+            &[1, 1, 1, 0, 0, 0, 0, 2, 2, 2],
+            &[2, 2, 2, 0, 0, 0, 0, 1, 1, 1],
+            &[2, 2, 2, 2, 2, 1, 1, 1, 1, 1],
+        ];
+
+        round_trip(&rows.concat(), 2);
+    }
+
+    #[test]
+    fn grow_code_size_and_clear_full_table() {
+        // pseudo random noise fills the table several times over
+        let mut state = 0x2545_F491u32;
+        let indices: Vec<u8> = (0..200_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+
+        round_trip(&indices, 8);
+        round_trip(&indices.iter().map(|idx| idx & 0b11).collect::<Vec<_>>(), 2);
+    }
+
+    #[test]
+    fn encode_uniform_and_empty_input() {
+        round_trip(&[0; 100_000], 2);
+        round_trip(&[], 4);
+    }
+
+    #[test]
+    fn limit_the_min_code_size() {
+        round_trip(&[1, 0, 1], 1);
+        round_trip(&[255, 3], 12);
+        assert_eq!(lzw_encode(&[1, 0], 0).lzw_min_code_size, 2);
+    }
+
+    #[test]
+    fn pick_min_code_size() {
+        assert_eq!(min_code_size(1), 2);
+        assert_eq!(min_code_size(4), 2);
+        assert_eq!(min_code_size(5), 3);
+        assert_eq!(min_code_size(256), 8);
+    }
+
+    #[test]
+    fn stay_within_the_error_budget() {
+        let grays: Vec<Color> = (0..=255)
+            .map(|idx| Color::from_triple((idx, idx, idx)))
+            .collect();
+        let mut state = 0x2545_F491u32;
+        // a noisy gradient with a transparent stripe
+        let indices: Vec<u8> = (0..128 * 128)
+            .map(|idx: u32| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                match idx % 128 {
+                    60..68 => 0,
+                    x => (x as u8 + (state % 8) as u8).max(1),
+                }
+            })
+            .collect();
+
+        let lossless = lzw_encode(&indices, 8);
+        let lossy = lzw_encode_lossy(&indices, 8, &grays, Some(0), 8);
+
+        let size = |data: &ImageData| {
+            data.sub_blocks
+                .iter()
+                .map(|block| block.0.len())
+                .sum::<usize>()
+        };
+        assert!(size(&lossy) < size(&lossless) * 3 / 4);
+
+        let decoded = lzw_decode(&lossy, indices.len()).unwrap();
+        for (original, decoded) in indices.iter().zip(&decoded) {
+            assert_eq!(*original == 0, *decoded == 0);
+            let original = grays[*original as usize];
+            assert!(
+                distance(
+                    [original.r, original.g, original.b],
+                    grays[*decoded as usize]
+                ) <= 64
+            );
+        }
+    }
+}
@@ -0,0 +1,441 @@
+use std::borrow::Cow;
+use std::io::Write;
+
+use delta::{Delta, DeltaCanvas};
+use dither::{DitherOptions, Shown, map_pixels};
+use quantize::{QuantizeOptions, Quantizer, palette_for};
+pub(crate) use tables::IndexedFrame;
+use tables::select_tables;
+use thiserror::Error;
+
+mod delta;
+pub mod dither;
+mod fit;
+mod lzw;
+mod optimize;
+mod palette;
+pub mod quantize;
+mod tables;
+mod writer;
+
+pub use fit::FitSettings;
+pub use lzw::{lzw_encode, lzw_encode_lossy, min_code_size};
+pub use optimize::optimize;
+
+pub use crate::gif::Rgba;
+use crate::gif::{
+    Block, Color, Gif, GifVersion, Rect,
+    descriptor::LogicalScreenDescriptor,
+    extension::{ApplicationExtension, DisposalMethod, NetScapeExtension},
+};
+
+#[derive(Debug, Error)]
+pub enum EncodeError {
+    #[error("Encountered invalid data while encoding GIF: {0}")]
+    InvalidData(Cow<'static, str>),
+    #[error("Io error while encoding GIF `{0}`")]
+    Io(#[from] std::io::Error),
+}
+
+/// Pixels with less alpha than this are written as transparent
+const ALPHA_THRESHOLD: u8 = 128;
+
+pub(crate) fn is_transparent(pixel: Rgba) -> bool {
+    pixel[3] < ALPHA_THRESHOLD
+}
+
+/// Creates GIF89a files from full canvas RGBA frames
+#[derive(Debug, Clone)]
+pub struct Encoder {
+    width: u16,
+    height: u16,
+    loop_count: Option<u16>,
+    quantize: QuantizeOptions,
+    dither: DitherOptions,
+    global_palette: bool,
+    delta_frames: bool,
+    lossy: u16,
+    frames: Vec<RgbaFrame>,
+}
+
+#[derive(Debug, Clone)]
+struct RgbaFrame {
+    pixels: Vec<Rgba>,
+    delay_time: u16,
+}
+
+impl Encoder {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            loop_count: None,
+            quantize: QuantizeOptions::default(),
+            dither: DitherOptions::default(),
+            global_palette: false,
+            delta_frames: true,
+            lossy: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Algorithm used for frames with more than 256 colors
+    pub fn quantizer(&mut self, quantizer: Quantizer) -> &mut Self {
+        self.quantize.quantizer = quantizer;
+        self
+    }
+
+    /// Quantization quality from 1 to 100, see [`QuantizeOptions::quality`]
+    pub fn quality(&mut self, quality: u8) -> &mut Self {
+        self.quantize.quality = quality.clamp(1, 100);
+        self
+    }
+
+    /// Opaque colors per palette at most, see [`QuantizeOptions::max_colors`]
+    pub fn max_colors(&mut self, max_colors: usize) -> &mut Self {
+        self.quantize.max_colors = max_colors;
+        self
+    }
+
+    pub fn dither(&mut self, dither: DitherOptions) -> &mut Self {
+        self.dither = dither;
+        self
+    }
+
+    /// Quantizes all frames together into one global color table instead of
+    /// giving every frame its own local table
+    pub fn global_palette(&mut self, global_palette: bool) -> &mut Self {
+        self.global_palette = global_palette;
+        self
+    }
+
+    /// Repeats the animation `loop_count` times, 0 loops forever. Without a
+    /// loop count the animation plays once.
+    pub fn loop_count(&mut self, loop_count: u16) -> &mut Self {
+        self.loop_count = Some(loop_count);
+        self
+    }
+
+    /// Crops every frame after the first to the pixels that changed and makes
+    /// unchanged pixels inside the crop transparent. Enabled by default.
+    pub fn delta_frames(&mut self, delta_frames: bool) -> &mut Self {
+        self.delta_frames = delta_frames;
+        self
+    }
+
+    /// Lets pixels be off by up to `max_error` in RGB space when that
+    /// compresses better, 0 keeps compression lossless. Transparent and
+    /// unchanged pixels are never affected.
+    pub fn lossy(&mut self, max_error: u16) -> &mut Self {
+        self.lossy = max_error;
+        self
+    }
+
+    /// Adds a frame from 4 bytes per pixel in row-major order, shown for
+    /// `delay_time` hundredths of a second
+    pub fn add_frame(&mut self, rgba: &[u8], delay_time: u16) -> Result<&mut Self, EncodeError> {
+        let expected = self.width as usize * self.height as usize * 4;
+        if rgba.len() != expected {
+            return Err(EncodeError::InvalidData(
+                format!("frame has {} bytes, expected {expected}", rgba.len()).into(),
+            ));
+        }
+
+        let pixels = rgba
+            .chunks_exact(4)
+            .map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]])
+            .collect();
+
+        self.frames.push(RgbaFrame { pixels, delay_time });
+
+        Ok(self)
+    }
+
+    pub fn encode(&self) -> Result<Gif, EncodeError> {
+        if self.frames.is_empty() {
+            return Err(EncodeError::InvalidData("no frames to encode".into()));
+        }
+
+        // delta frames need a transparent index for unchanged pixels
+        let reserve_transparency = self.delta_frames;
+        let mut global = self.global_palette.then(|| {
+            let frames: Vec<&[Rgba]> = self.frames.iter().map(|frame| &frame.pixels[..]).collect();
+            palette_for(&frames, self.quantize, reserve_transparency)
+        });
+
+        let mut indexed = Vec::with_capacity(self.frames.len());
+        let mut delta = self
+            .delta_frames
+            .then(|| DeltaCanvas::new(self.width, self.height));
+        let mut previous: Option<(&RgbaFrame, Vec<Option<Color>>)> = None;
+
+        for frame in &self.frames {
+            let mut local_palette;
+            let palette = match &mut global {
+                Some(palette) => palette,
+                None => {
+                    local_palette =
+                        palette_for(&[&frame.pixels], self.quantize, reserve_transparency);
+                    &mut local_palette
+                }
+            };
+
+            let shown = previous.as_ref().map(|(frame, colors)| Shown {
+                pixels: &frame.pixels,
+                colors,
+            });
+            let indices = map_pixels(
+                &frame.pixels,
+                self.width as usize,
+                palette,
+                self.dither,
+                shown,
+            );
+            let transparent_idx = palette.transparent_idx();
+            let colors: Vec<Option<Color>> = indices
+                .iter()
+                .map(|idx| (Some(*idx) != transparent_idx).then(|| palette.colors()[*idx as usize]))
+                .collect();
+
+            match &mut delta {
+                Some(delta) => {
+                    if let Some(delta) = delta.push(colors.clone(), frame.delay_time) {
+                        indexed.push(indexed_delta(delta));
+                    }
+                }
+                None => {
+                    // the previous frame clears itself once shown where this
+                    // one has transparent pixels over its opaque ones
+                    let shows_through = previous.as_ref().is_some_and(|(_, shown)| {
+                        colors
+                            .iter()
+                            .zip(shown)
+                            .any(|(color, shown)| color.is_none() && shown.is_some())
+                    });
+                    if shows_through && let Some(previous) = indexed.last_mut() {
+                        previous.disposal = DisposalMethod::RestoreBackground;
+                    }
+                    indexed.push(IndexedFrame {
+                        rect: Rect::new(0, 0, self.width, self.height),
+                        delay_time: frame.delay_time,
+                        disposal: DisposalMethod::Keep,
+                        indices,
+                        table: palette.color_table(),
+                        transparent_idx,
+                    });
+                }
+            }
+
+            previous = Some((frame, colors));
+        }
+
+        if let Some(delta) = delta.and_then(DeltaCanvas::finish) {
+            indexed.push(indexed_delta(delta));
+        }
+
+        let (gct, images) = select_tables(
+            indexed,
+            global.map(|palette| palette.color_table()),
+            self.lossy,
+        );
+
+        let mut blocks = Vec::with_capacity(images.len() + 1);
+        if let Some(loop_count) = self.loop_count {
+            blocks.push(Block::ApplicationExtension(ApplicationExtension::NetScape(
+                NetScapeExtension::with_loop_count(loop_count),
+            )));
+        }
+        blocks.extend(images);
+
+        Ok(Gif::from_tuple((
+            GifVersion::V89a,
+            LogicalScreenDescriptor::new(self.width, self.height, gct.as_ref(), 0),
+            gct,
+            blocks,
+        )))
+    }
+
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), EncodeError> {
+        self.encode()?.write_to(writer)
+    }
+}
+
+fn indexed_delta(delta: Delta<u16>) -> IndexedFrame {
+    IndexedFrame::from_pixels(delta.rect, delta.data, delta.disposal, &delta.pixels)
+        .expect("deltas only hold colors of a palette")
+}
+
+#[cfg(test)]
+mod should {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::decoder::{TRANSPARENT, composite, decode, parse};
+
+    const RED: Rgba = [255, 0, 0, 255];
+    const BLUE: Rgba = [0, 0, 255, 255];
+    const CLEAR: Rgba = [0, 0, 0, 0];
+
+    fn rgba(pixels: &[Rgba]) -> Vec<u8> {
+        pixels.concat()
+    }
+
+    fn encode_and_parse(encoder: &Encoder) -> Gif {
+        let mut bytes = Vec::new();
+        encoder.write_to(&mut bytes).unwrap();
+
+        parse(bytes.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn encode_animation_that_parses_back() {
+        let mut encoder = Encoder::new(2, 2);
+        encoder.loop_count(0).delta_frames(false);
+        encoder
+            .add_frame(&rgba(&[RED, RED, BLUE, BLUE]), 10)
+            .unwrap()
+            .add_frame(&rgba(&[CLEAR, BLUE, RED, CLEAR]), 20)
+            .unwrap();
+
+        let gif = encode_and_parse(&encoder);
+        let Block::ApplicationExtension(ApplicationExtension::NetScape(netscape)) = &gif.blocks[0]
+        else {
+            panic!("expected loop extension first");
+        };
+        assert_eq!(netscape.loop_count(), Some(0));
+
+        let shown = composite(&gif).unwrap();
+        assert_eq!(shown[1].pixels, vec![TRANSPARENT, BLUE, RED, TRANSPARENT]);
+
+        let frames = decode(gif).unwrap();
+        assert_eq!(frames.len(), 2);
+
+        let red = Color::from_triple((255, 0, 0));
+        let blue = Color::from_triple((0, 0, 255));
+        assert_eq!(frames[0].pixels, vec![red, red, blue, blue]);
+        assert_eq!(frames[0].transparent_color_idx, None);
+        assert_eq!(frames[0].disposal, DisposalMethod::RestoreBackground);
+
+        let second = &frames[1];
+        assert!(second.is_transparent(0) && second.is_transparent(3));
+        assert_eq!(&second.pixels[1..3], &[blue, red]);
+        assert_eq!(second.disposal, DisposalMethod::Keep);
+        assert_eq!(second.delay, Duration::from_millis(200));
+    }
+
+    #[test]
+    fn map_truecolor_frames_to_a_palette() {
+        let mut encoder = Encoder::new(32, 32);
+        let gradient: Vec<Rgba> = (0..32 * 32)
+            .map(|i| [(i % 32 * 8) as u8, (i / 32 * 8) as u8, 128, 255])
+            .collect();
+        encoder.add_frame(&rgba(&gradient), 0).unwrap();
+
+        let frames = decode(encode_and_parse(&encoder)).unwrap();
+
+        let top_right = frames[0].pixels[31];
+        assert!(top_right.r > 200 && top_right.g < 50);
+    }
+
+    #[test]
+    fn share_a_global_palette() {
+        let mut encoder = Encoder::new(2, 1);
+        encoder.global_palette(true).quantizer(Quantizer::Octree);
+        encoder
+            .add_frame(&rgba(&[RED, RED]), 0)
+            .unwrap()
+            .add_frame(&rgba(&[BLUE, CLEAR]), 0)
+            .unwrap();
+
+        let gif = encode_and_parse(&encoder);
+
+        // the second frame only draws blue, so the table needs no transparency
+        assert_eq!(gif.gct.as_ref().map(|gct| gct.len()), Some(2));
+        assert!(gif.images().all(|(_, _, image)| image.lct.is_none()));
+        let composited = composite(&gif).unwrap();
+        assert_eq!(composited[1].pixels, vec![BLUE, TRANSPARENT]);
+    }
+
+    #[test]
+    fn crop_frames_to_changed_pixels() {
+        let square_at = |left: usize, hole: bool| {
+            let pixels: Vec<Rgba> = (0..8 * 8)
+                .map(|idx| match (idx % 8, idx / 8) {
+                    (x, 2..4) if (left..left + 2).contains(&x) => RED,
+                    (4, 6) if hole => CLEAR,
+                    _ => BLUE,
+                })
+                .collect();
+            pixels
+        };
+        let inputs = [
+            square_at(0, false),
+            square_at(1, false),
+            square_at(1, false),
+            square_at(1, true),
+        ];
+
+        let mut encoder = Encoder::new(8, 8);
+        for input in &inputs {
+            encoder.add_frame(&rgba(input), 10).unwrap();
+        }
+        let gif = encode_and_parse(&encoder);
+
+        let rects: Vec<Rect> = gif
+            .images()
+            .map(|(_, _, image)| image.descriptor.rect())
+            .collect();
+        assert_eq!(rects[0], Rect::new(0, 0, 8, 8));
+        assert_eq!(rects[1], Rect::new(0, 2, 3, 2));
+        // the unchanged third frame only covers the pixel it clears for the hole
+        assert_eq!(rects[2], Rect::new(4, 6, 1, 1));
+        assert_eq!(rects[3], Rect::new(0, 0, 1, 1));
+
+        let composited = composite(&gif).unwrap();
+        for (input, frame) in inputs.iter().zip(&composited) {
+            let expected: Vec<Rgba> = input
+                .iter()
+                .map(|pixel| if *pixel == CLEAR { TRANSPARENT } else { *pixel })
+                .collect();
+            assert_eq!(frame.pixels, expected);
+        }
+    }
+
+    #[test]
+    fn trade_accuracy_for_size_when_lossy() {
+        let noisy: Vec<Rgba> = (0..64 * 64u32)
+            .map(|idx| {
+                let noise = (idx.wrapping_mul(2_654_435_761) >> 28) as u8;
+                [(idx % 64 * 3) as u8 + noise, 96, 160, 255]
+            })
+            .collect();
+        let encode = |max_error| {
+            let mut encoder = Encoder::new(64, 64);
+            encoder
+                .lossy(max_error)
+                .add_frame(&rgba(&noisy), 0)
+                .unwrap();
+            encoder.encode().unwrap()
+        };
+
+        let (lossless, lossy) = (encode(0), encode(24));
+
+        assert!(lossy.to_bytes().unwrap().len() < lossless.to_bytes().unwrap().len());
+        let exact = &composite(&lossless).unwrap()[0].pixels;
+        let approximate = &composite(&lossy).unwrap()[0].pixels;
+        for (exact, approximate) in exact.iter().zip(approximate) {
+            let distance: u32 = (0..3)
+                .map(|c| (exact[c].abs_diff(approximate[c]) as u32).pow(2))
+                .sum();
+            assert!(distance <= 24 * 24);
+        }
+    }
+
+    #[test]
+    fn reject_frames_of_wrong_size() {
+        let mut encoder = Encoder::new(2, 2);
+
+        assert!(encoder.add_frame(&rgba(&[RED]), 0).is_err());
+        assert!(encoder.encode().is_err());
+    }
+}
@@ -0,0 +1,325 @@
+use super::{
+    delta::{Delta, DeltaCanvas},
+    tables::{IndexedFrame, select_tables},
+};
+use crate::decoder::{Compositor, decode_indices};
+use crate::gif::{
+    Block, Color, Gif, ImageBlock, ImageContent, SubBlock,
+    extension::{CommentExtension, DisposalMethod},
+};
+
+/// Rewrites `gif` to be as small as possible without changing how it renders.
+///
+/// Frames are cropped to the pixels they change, frames that change nothing
+/// are merged into the frame before, color tables only keep the colors in
+/// use and image data is compressed again. The input is returned unchanged
+/// when it can not be decoded or the rewrite would not be smaller.
+pub fn optimize(gif: &Gif) -> Gif {
+    let candidates = [delta_frames(gif), recompressed(gif)];
+
+    let size = |gif: &Gif| gif.encoded_len().unwrap_or(usize::MAX);
+    let original = size(gif);
+
+    candidates
+        .into_iter()
+        .flatten()
+        .map(|indexed| rebuild(gif, indexed))
+        .map(|candidate| (size(&candidate), candidate))
+        .filter(|(candidate, _)| *candidate < original)
+        .min_by_key(|(size, _)| *size)
+        .map_or_else(|| gif.clone(), |(_, candidate)| candidate)
+}
+
+/// Image frames keyed by the block they replace
+type Rewrite = Vec<(usize, IndexedFrame)>;
+
+/// Frames rebuilt from the composited canvas, `None` if a frame would need
+/// more than 256 colors
+fn delta_frames(gif: &Gif) -> Option<Rewrite> {
+    // plain text is drawn by some viewers, so its frames are left alone
+    let has_plain_text = gif.blocks.iter().any(|block| {
+        matches!(
+            block,
+            Block::Image(image) if matches!(image.content, ImageContent::PlainText(_))
+        )
+    });
+    if has_plain_text {
+        return None;
+    }
+
+    let mut canvas = DeltaCanvas::new(gif.lsd.canvas_width, gif.lsd.canvas_height);
+    let mut indexed = Vec::new();
+    let mut pending: Option<(Vec<Option<Color>>, u16, usize)> = None;
+
+    for frame in Compositor::new(gif) {
+        let frame = frame.ok()?;
+        let target: Vec<Option<Color>> = frame
+            .pixels
+            .iter()
+            .map(|[r, g, b, a]| {
+                (*a != 0).then_some(Color {
+                    r: *r,
+                    g: *g,
+                    b: *b,
+                })
+            })
+            .collect();
+
+        // showing the same canvas longer, unless the delay overflows or a
+        // frame waits for user input
+        let merged = match &pending {
+            Some((shown, delay_time, block_idx))
+                if *shown == target
+                    && !user_input(gif, *block_idx)
+                    && !user_input(gif, frame.block_idx) =>
+            {
+                delay_time.checked_add(frame.delay_time)
+            }
+            _ => None,
+        };
+
+        match (&mut pending, merged) {
+            (Some((_, delay_time, _)), Some(merged)) => *delay_time = merged,
+            _ => {
+                let next = (target, frame.delay_time, frame.block_idx);
+                if let Some((target, delay_time, block_idx)) = pending.replace(next)
+                    && let Some(delta) = canvas.push(target, (delay_time, block_idx))
+                {
+                    indexed.push(indexed_delta(delta)?);
+                }
+            }
+        }
+    }
+
+    let (target, delay_time, block_idx) = pending?;
+    if let Some(delta) = canvas.push(target, (delay_time, block_idx)) {
+        indexed.push(indexed_delta(delta)?);
+    }
+    if let Some(delta) = canvas.finish() {
+        indexed.push(indexed_delta(delta)?);
+    }
+
+    Some(indexed)
+}
+
+fn user_input(gif: &Gif, block_idx: usize) -> bool {
+    matches!(
+        &gif.blocks[block_idx],
+        Block::Image(ImageBlock { gce: Some(gce), .. }) if gce.user_input()
+    )
+}
+
+fn indexed_delta(delta: Delta<(u16, usize)>) -> Option<(usize, IndexedFrame)> {
+    let (delay_time, block_idx) = delta.data;
+    let frame = IndexedFrame::from_pixels(delta.rect, delay_time, delta.disposal, &delta.pixels)?;
+
+    Some((block_idx, frame))
+}
+
+/// Frames as they are, only with smaller tables and compressed again
+fn recompressed(gif: &Gif) -> Option<Rewrite> {
+    gif.images()
+        .map(|(block_idx, block, image)| {
+            let gce = block.gce.as_ref();
+            let table = image.lct.as_ref().or(gif.gct.as_ref())?;
+            let transparent_idx = gce.and_then(|gce| gce.transparent_color_idx());
+            let indices = decode_indices(image).ok()?;
+
+            // decoders draw indices past the table in black, which a pruned
+            // table can not hold
+            let is_valid =
+                |idx: &u8| (*idx as usize) < table.len() || Some(*idx) == transparent_idx;
+            if !indices.iter().all(is_valid) {
+                return None;
+            }
+
+            Some((
+                block_idx,
+                IndexedFrame {
+                    rect: image.descriptor.rect(),
+                    delay_time: gce.map_or(0, |gce| gce.delay_time()),
+                    disposal: gce.map_or(DisposalMethod::Unspecified, |gce| gce.disposal_method()),
+                    indices,
+                    table: table.clone(),
+                    transparent_idx,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Replaces the image blocks of `gif` and keeps every other block in place,
+/// images without a replacement are dropped
+fn rebuild(gif: &Gif, indexed: Rewrite) -> Gif {
+    let (block_indices, frames): (Vec<usize>, Vec<IndexedFrame>) = indexed.into_iter().unzip();
+    let (gct, images) = select_tables(frames, gif.gct.clone(), 0);
+    let mut images = block_indices.into_iter().zip(images).peekable();
+
+    let mut blocks = Vec::with_capacity(gif.blocks.len());
+    for (block_idx, block) in gif.blocks.iter().enumerate() {
+        match block {
+            Block::Image(ImageBlock {
+                content: ImageContent::Image(_),
+                ..
+            }) => {
+                if let Some((_, mut image)) = images.next_if(|(idx, _)| *idx == block_idx) {
+                    if let Block::Image(ImageBlock { gce: Some(gce), .. }) = &mut image {
+                        gce.set_user_input(user_input(gif, block_idx));
+                    }
+                    blocks.push(image);
+                }
+            }
+            Block::CommentExtension(comment) => blocks.push(Block::CommentExtension(
+                CommentExtension::new(SubBlock::chunks(&comment.text())),
+            )),
+            block => blocks.push(block.clone()),
+        }
+    }
+
+    let mut lsd = gif.lsd.clone();
+    lsd.set_global_color_table(gct.as_ref());
+
+    Gif::from_tuple((gif.version.clone(), lsd, gct, blocks))
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::decoder::{composite, parse};
+    use crate::encoder::{Encoder, Rgba, lzw_encode};
+    use crate::gif::builder::{FrameBuilder, GifBuilder};
+
+    /// Canvases in the order they are shown and for how long
+    fn rendered(gif: &Gif) -> Vec<(Vec<Rgba>, u32)> {
+        let mut shown: Vec<(Vec<Rgba>, u32)> = Vec::new();
+
+        for frame in composite(gif).unwrap() {
+            match shown.last_mut() {
+                Some((pixels, delay)) if *pixels == frame.pixels => {
+                    *delay += frame.delay_time as u32
+                }
+                _ => shown.push((frame.pixels, frame.delay_time as u32)),
+            }
+        }
+
+        shown
+    }
+
+    #[test]
+    fn shrink_without_changing_the_animation() {
+        // a square moving over a gradient, every frame covers the canvas
+        let mut encoder = Encoder::new(16, 16);
+        encoder.delta_frames(false).loop_count(0);
+        for frame in 0..4 {
+            let pixels: Vec<Rgba> = (0..16 * 16)
+                .map(|idx| (idx % 16, idx / 16))
+                .map(
+                    |(x, y)| match (frame..frame + 4).contains(&x) && (4..8).contains(&y) {
+                        true => [255, 0, 0, 255],
+                        false => [x as u8 * 16, y as u8 / 4 * 64, 100, 255],
+                    },
+                )
+                .collect();
+            encoder.add_frame(&pixels.concat(), 10).unwrap();
+        }
+        let gif = encoder.encode().unwrap();
+
+        let optimized = optimize(&gif);
+
+        assert!(optimized.to_bytes().unwrap().len() < gif.to_bytes().unwrap().len());
+        let reparsed = parse(optimized.to_bytes().unwrap().as_slice()).unwrap();
+        assert!(rendered(&reparsed) == rendered(&gif));
+    }
+
+    #[test]
+    fn merge_repeated_frames() {
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let frame = |pixel: Rgba| [pixel; 16 * 16].concat();
+
+        let mut encoder = Encoder::new(16, 16);
+        encoder.delta_frames(false).loop_count(0);
+        for (pixel, delay_time) in [(red, 10), (red, 20), (blue, 5), (blue, 5), (red, 1)] {
+            encoder.add_frame(&frame(pixel), delay_time).unwrap();
+        }
+        let mut gif = encoder.encode().unwrap();
+        gif.blocks
+            .push(Block::CommentExtension(CommentExtension::new(vec![
+                SubBlock(b"made ".to_vec()),
+                SubBlock(b"by hand".to_vec()),
+            ])));
+
+        let optimized = optimize(&gif);
+
+        let delays: Vec<u16> = composite(&optimized)
+            .unwrap()
+            .iter()
+            .map(|frame| frame.delay_time)
+            .collect();
+        assert_eq!(delays, vec![30, 10, 1]);
+        assert!(rendered(&optimized) == rendered(&gif));
+        assert!(matches!(
+            optimized.blocks[0],
+            Block::ApplicationExtension(_)
+        ));
+        let Some(Block::CommentExtension(comment)) = optimized.blocks.last() else {
+            panic!("expected the comment to be kept");
+        };
+        assert_eq!(comment.sub_blocks, vec![SubBlock(b"made by hand".to_vec())]);
+    }
+
+    #[test]
+    fn keep_frames_with_indices_past_the_table() {
+        let colors = vec![
+            Color::from_triple((255, 0, 0)),
+            Color::from_triple((0, 0, 255)),
+        ];
+        let mut gif = GifBuilder::new(1, 1)
+            .global_palette(colors)
+            .frame(&FrameBuilder::new(1, 1, vec![1]))
+            .build()
+            .unwrap();
+        let Block::Image(ImageBlock {
+            content: ImageContent::Image(image),
+            ..
+        }) = &mut gif.blocks[0]
+        else {
+            panic!("expected an image");
+        };
+        image.data = lzw_encode(&[3], 2);
+
+        let optimized = optimize(&gif);
+
+        assert!(rendered(&optimized) == rendered(&gif));
+    }
+
+    #[test]
+    fn keep_frames_apart_when_merging_changes_timing() {
+        let red = [[255, 0, 0, 255]; 4].concat();
+        let mut encoder = Encoder::new(2, 2);
+        encoder.delta_frames(false);
+        for delay_time in [60_000, 60_000, 5, 5] {
+            encoder.add_frame(&red, delay_time).unwrap();
+        }
+        let mut gif = encoder.encode().unwrap();
+        let Block::Image(ImageBlock { gce: Some(gce), .. }) = &mut gif.blocks[2] else {
+            panic!("expected an image");
+        };
+        gce.set_user_input(true);
+
+        let optimized = optimize(&gif);
+
+        let frames = composite(&optimized).unwrap();
+        let delays: Vec<u16> = frames.iter().map(|frame| frame.delay_time).collect();
+        assert_eq!(delays, vec![60_000, 60_000, 5, 5]);
+        let user_input: Vec<bool> = optimized
+            .blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Image(image) => image.gce.as_ref().map(|gce| gce.user_input()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(user_input, vec![false, false, true, false]);
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use super::{Rgba, is_transparent};
+use crate::gif::{
+    Color,
+    table::{ColorTable, MAX_COLORS},
+};
+
+/// Colors a frame is mapped onto, with an optional transparent entry
+pub(crate) struct Palette {
+    colors: Vec<Color>,
+    transparent_idx: Option<u8>,
+    /// Color indices ordered by their red channel
+    by_red: Vec<u8>,
+    cache: HashMap<[u8; 3], u8>,
+}
+
+impl Palette {
+    /// Opaque colors followed by a transparent entry when needed
+    pub(crate) fn new(mut colors: Vec<Color>, has_transparency: bool) -> Self {
+        let transparent_idx = has_transparency.then_some(colors.len() as u8);
+
+        if has_transparency {
+            colors.push(Color::from_triple((0, 0, 0)));
+        }
+
+        Palette::with_transparent_idx(colors, transparent_idx)
+    }
+
+    /// Palette of an existing color table, the transparent entry is never
+    /// picked for opaque pixels
+    pub(crate) fn with_transparent_idx(colors: Vec<Color>, transparent_idx: Option<u8>) -> Self {
+        assert!(colors.len() <= MAX_COLORS);
+
+        let mut by_red: Vec<u8> = (0..colors.len())
+            .map(|idx| idx as u8)
+            .filter(|idx| Some(*idx) != transparent_idx)
+            .collect();
+        by_red.sort_unstable_by_key(|idx| colors[*idx as usize].r);
+
+        Self {
+            colors,
+            transparent_idx,
+            by_red,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    pub(crate) fn transparent_idx(&self) -> Option<u8> {
+        self.transparent_idx
+    }
+
+    pub(crate) fn index_of(&mut self, pixel: Rgba) -> u8 {
+        let [r, g, b, _] = pixel;
+
+        match self.transparent_idx {
+            Some(transparent_idx) if is_transparent(pixel) => transparent_idx,
+            _ => {
+                if let Some(idx) = self.cache.get(&[r, g, b]) {
+                    return *idx;
+                }
+
+                let idx = self.nearest([r, g, b]);
+                self.cache.insert([r, g, b], idx);
+                idx
+            }
+        }
+    }
+
+    /// Index of the opaque color closest to `rgb`.
+    ///
+    /// Searches outwards from the closest red value and stops once the red
+    /// difference alone exceeds the best distance found.
+    pub(crate) fn nearest(&self, rgb: [u8; 3]) -> u8 {
+        let start = self
+            .by_red
+            .partition_point(|idx| self.colors[*idx as usize].r < rgb[0]);
+        let (below, above) = self.by_red.split_at(start);
+
+        let mut best = (u32::MAX, 0);
+        let mut below = below.iter().rev().peekable();
+        let mut above = above.iter().peekable();
+
+        loop {
+            let red_gap = |idx: &&u8| (rgb[0] as i32 - self.colors[**idx as usize].r as i32).pow(2);
+            let next = match (below.peek().map(red_gap), above.peek().map(red_gap)) {
+                (Some(down), Some(up)) if down <= up => below.next(),
+                (Some(_), Some(_)) | (None, Some(_)) => above.next(),
+                (Some(_), None) => below.next(),
+                (None, None) => None,
+            };
+
+            let Some(idx) = next else {
+                break;
+            };
+
+            if red_gap(&idx) as u32 > best.0 {
+                break;
+            }
+
+            let candidate = distance(rgb, self.colors[*idx as usize]);
+            if candidate < best.0 {
+                best = (candidate, *idx);
+            }
+        }
+
+        best.1
+    }
+
+    /// Padded color table including the transparent entry
+    pub(crate) fn color_table(&self) -> ColorTable {
+        ColorTable::new(self.colors.clone()).padded()
+    }
+}
+
+/// Squared euclidean distance in RGB space
+pub(crate) fn distance([r, g, b]: [u8; 3], color: Color) -> u32 {
+    let dr = r as i32 - color.r as i32;
+    let dg = g as i32 - color.g as i32;
+    let db = b as i32 - color.b as i32;
+
+    (dr * dr + dg * dg + db * db) as u32
+}
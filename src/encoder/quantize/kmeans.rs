@@ -0,0 +1,36 @@
+use super::{WeightedColor, mean};
+use crate::encoder::palette::Palette;
+use crate::gif::Color;
+
+/// Moves every palette color to the average of the colors closest to it,
+/// `iterations` times or until nothing moves
+pub(crate) fn refine(
+    histogram: &[WeightedColor],
+    mut palette: Vec<Color>,
+    iterations: usize,
+) -> Vec<Color> {
+    for _ in 0..iterations {
+        let mut clusters: Vec<Vec<WeightedColor>> = vec![Vec::new(); palette.len()];
+        let lookup = Palette::new(palette.clone(), false);
+
+        for (rgb, count) in histogram {
+            clusters[lookup.nearest(*rgb) as usize].push((*rgb, *count));
+        }
+
+        let refined: Vec<Color> = clusters
+            .iter()
+            .zip(&palette)
+            .map(|(cluster, color)| match cluster.is_empty() {
+                true => *color,
+                false => mean(cluster),
+            })
+            .collect();
+
+        if refined == palette {
+            break;
+        }
+        palette = refined;
+    }
+
+    palette
+}
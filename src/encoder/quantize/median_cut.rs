@@ -0,0 +1,89 @@
+use super::{WeightedColor, mean};
+use crate::gif::Color;
+
+/// Splits the box with the most pixels spread over the widest channel until
+/// there are `max_colors` boxes, each box becomes its average color
+pub(crate) fn quantize(histogram: &[WeightedColor], max_colors: usize) -> Vec<Color> {
+    let mut boxes = vec![ColorBox::new(histogram.to_vec())];
+
+    while boxes.len() < max_colors {
+        let Some((idx, _)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, color_box)| color_box.colors.len() > 1)
+            .max_by_key(|(_, color_box)| color_box.priority())
+        else {
+            break;
+        };
+
+        let (first, second) = boxes.swap_remove(idx).split();
+        boxes.push(first);
+        boxes.push(second);
+    }
+
+    boxes
+        .iter()
+        .filter(|color_box| !color_box.colors.is_empty())
+        .map(|color_box| mean(&color_box.colors))
+        .collect()
+}
+
+struct ColorBox {
+    colors: Vec<WeightedColor>,
+    pixels: u64,
+    /// Channel with the largest range and that range
+    widest_channel: (usize, u8),
+}
+
+impl ColorBox {
+    fn new(colors: Vec<WeightedColor>) -> Self {
+        let pixels = colors.iter().map(|(_, count)| *count as u64).sum();
+        let mut min = [u8::MAX; 3];
+        let mut max = [u8::MIN; 3];
+
+        for (rgb, _) in &colors {
+            for channel in 0..3 {
+                min[channel] = min[channel].min(rgb[channel]);
+                max[channel] = max[channel].max(rgb[channel]);
+            }
+        }
+
+        let widest_channel = (0..3)
+            .map(|channel| (channel, max[channel].saturating_sub(min[channel])))
+            .max_by_key(|(_, range)| *range)
+            .expect("three channels");
+
+        Self {
+            colors,
+            pixels,
+            widest_channel,
+        }
+    }
+
+    fn priority(&self) -> u64 {
+        let (_, range) = self.widest_channel;
+        self.pixels * range as u64
+    }
+
+    /// Splits at the pixel weighted median of the widest channel
+    fn split(mut self) -> (ColorBox, ColorBox) {
+        let (channel, _) = self.widest_channel;
+        self.colors.sort_unstable_by_key(|(rgb, _)| rgb[channel]);
+
+        let half = self.pixels / 2;
+        let mut seen = 0;
+        let mut at = self.colors.len() - 1;
+
+        for (idx, (_, count)) in self.colors.iter().enumerate() {
+            seen += *count as u64;
+            if seen >= half {
+                at = idx + 1;
+                break;
+            }
+        }
+
+        let second = self.colors.split_off(at.clamp(1, self.colors.len() - 1));
+
+        (ColorBox::new(self.colors), ColorBox::new(second))
+    }
+}
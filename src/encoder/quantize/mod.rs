@@ -0,0 +1,271 @@
+//! Reduces truecolor pixels to a color table of at most 256 entries
+
+use std::collections::HashMap;
+
+use super::palette::Palette;
+use super::{Rgba, is_transparent};
+use crate::gif::{Color, table::MAX_COLORS};
+
+mod kmeans;
+mod median_cut;
+mod octree;
+
+/// Algorithm used to pick palette colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Quantizer {
+    /// Splits the color space at the median of its widest channel
+    #[default]
+    MedianCut,
+    /// Merges the least populated branches of an RGB octree
+    Octree,
+    /// Refines a median cut palette with k-means iterations, slowest but
+    /// with the least error
+    KMeans,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuantizeOptions {
+    pub quantizer: Quantizer,
+    /// 1 to 100, lower qualities sample fewer pixels and refine less
+    pub quality: u8,
+    /// Opaque colors to pick at most, one entry is kept free for transparency
+    /// when needed
+    pub max_colors: usize,
+}
+
+impl Default for QuantizeOptions {
+    fn default() -> Self {
+        Self {
+            quantizer: Quantizer::default(),
+            quality: 100,
+            max_colors: MAX_COLORS,
+        }
+    }
+}
+
+impl QuantizeOptions {
+    /// Every n-th pixel is sampled
+    fn sample_stride(&self) -> usize {
+        1 + (100 - self.quality.clamp(1, 100) as usize) / 10
+    }
+
+    fn kmeans_iterations(&self) -> usize {
+        1 + self.quality.clamp(1, 100) as usize / 20
+    }
+}
+
+/// Palette picked by a quantizer and every frame mapped onto it
+#[derive(Debug, Clone)]
+pub struct Quantized {
+    pub colors: Vec<Color>,
+    /// Index used for transparent pixels, placed after the opaque colors
+    pub transparent_idx: Option<u8>,
+    /// Palette indices of each frame in row-major order
+    pub indices: Vec<Vec<u8>>,
+}
+
+/// Quantizes a single frame
+pub fn quantize(pixels: &[Rgba], options: QuantizeOptions) -> Quantized {
+    quantize_frames(&[pixels], options)
+}
+
+/// Quantizes all frames together into one shared palette
+pub fn quantize_frames(frames: &[&[Rgba]], options: QuantizeOptions) -> Quantized {
+    let mut palette = palette_for(frames, options, false);
+    let indices = frames
+        .iter()
+        .map(|pixels| {
+            pixels
+                .iter()
+                .map(|pixel| palette.index_of(*pixel))
+                .collect()
+        })
+        .collect();
+
+    // the transparent entry is the last one
+    let opaque = palette.colors().len() - palette.transparent_idx().is_some() as usize;
+
+    Quantized {
+        colors: palette.colors()[..opaque].to_vec(),
+        transparent_idx: palette.transparent_idx(),
+        indices,
+    }
+}
+
+/// Picks a palette for `frames`, exact colors are kept when they fit. A
+/// transparent entry is added when a pixel is transparent or when
+/// `reserve_transparency` is set, unless the exact colors fill the table.
+pub(crate) fn palette_for(
+    frames: &[&[Rgba]],
+    options: QuantizeOptions,
+    reserve_transparency: bool,
+) -> Palette {
+    let has_transparent_pixels = frames
+        .iter()
+        .any(|pixels| pixels.iter().any(|pixel| is_transparent(*pixel)));
+    let max_colors = options
+        .max_colors
+        .clamp(1, MAX_COLORS - has_transparent_pixels as usize);
+
+    if let Some(colors) = exact_colors(frames, max_colors) {
+        let has_transparency =
+            has_transparent_pixels || (reserve_transparency && colors.len() < MAX_COLORS);
+        return Palette::new(colors, has_transparency);
+    }
+
+    let has_transparency = has_transparent_pixels || reserve_transparency;
+    let max_colors = max_colors.min(MAX_COLORS - has_transparency as usize);
+
+    let histogram = histogram(frames, options.sample_stride());
+    let colors = match options.quantizer {
+        Quantizer::MedianCut => median_cut::quantize(&histogram, max_colors),
+        Quantizer::Octree => octree::quantize(&histogram, max_colors),
+        Quantizer::KMeans => {
+            let seed = median_cut::quantize(&histogram, max_colors);
+            kmeans::refine(&histogram, seed, options.kmeans_iterations())
+        }
+    };
+
+    Palette::new(colors, has_transparency)
+}
+
+/// Distinct opaque colors, unless there are more than `max_colors`
+fn exact_colors(frames: &[&[Rgba]], max_colors: usize) -> Option<Vec<Color>> {
+    let mut seen = HashMap::new();
+    let mut colors = Vec::new();
+
+    for [r, g, b, a] in frames.iter().flat_map(|pixels| pixels.iter()) {
+        if is_transparent([*r, *g, *b, *a]) || seen.insert([*r, *g, *b], ()).is_some() {
+            continue;
+        }
+
+        if colors.len() == max_colors {
+            return None;
+        }
+
+        colors.push(Color::from_triple((*r, *g, *b)));
+    }
+
+    Some(colors)
+}
+
+/// Opaque color and how many sampled pixels have it
+pub(crate) type WeightedColor = ([u8; 3], u32);
+
+fn histogram(frames: &[&[Rgba]], stride: usize) -> Vec<WeightedColor> {
+    let mut counts: HashMap<[u8; 3], u32> = HashMap::new();
+
+    for pixels in frames {
+        for [r, g, b, a] in pixels.iter().step_by(stride) {
+            if !is_transparent([*r, *g, *b, *a]) {
+                *counts.entry([*r, *g, *b]).or_default() += 1;
+            }
+        }
+    }
+
+    // sorted so that equal input always gives the same palette
+    let mut histogram: Vec<WeightedColor> = counts.into_iter().collect();
+    histogram.sort_unstable();
+
+    histogram
+}
+
+/// Weighted average color
+pub(crate) fn mean(colors: &[WeightedColor]) -> Color {
+    let mut sums = [0u64; 3];
+    let mut total = 0u64;
+
+    for (rgb, count) in colors {
+        for (sum, channel) in sums.iter_mut().zip(rgb) {
+            *sum += *channel as u64 * *count as u64;
+        }
+        total += *count as u64;
+    }
+
+    let [r, g, b] = sums.map(|sum| (sum + total / 2).checked_div(total).unwrap_or(0) as u8);
+
+    Color::from_triple((r, g, b))
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::encoder::palette::distance;
+
+    fn gradient() -> Vec<Rgba> {
+        (0..64 * 64)
+            .map(|i| {
+                [
+                    (i % 64 * 4) as u8,
+                    (i / 64 * 4) as u8,
+                    (i % 7 * 30) as u8,
+                    255,
+                ]
+            })
+            .collect()
+    }
+
+    fn mean_error(pixels: &[Rgba], quantized: &Quantized) -> f64 {
+        let total: u64 = pixels
+            .iter()
+            .zip(&quantized.indices[0])
+            .map(|([r, g, b, _], idx)| {
+                distance([*r, *g, *b], quantized.colors[*idx as usize]) as u64
+            })
+            .sum();
+
+        total as f64 / pixels.len() as f64
+    }
+
+    #[test]
+    fn reduce_colors_with_every_quantizer() {
+        let pixels = gradient();
+
+        for quantizer in [Quantizer::MedianCut, Quantizer::Octree, Quantizer::KMeans] {
+            let options = QuantizeOptions {
+                quantizer,
+                max_colors: 32,
+                ..Default::default()
+            };
+            let quantized = quantize(&pixels, options);
+
+            assert!(quantized.colors.len() <= 32, "{quantizer:?}");
+            assert_eq!(quantized.indices[0].len(), pixels.len());
+            // 32 colors spread over this gradient are roughly 30 steps apart
+            // per channel
+            assert!(mean_error(&pixels, &quantized) < 2500.0, "{quantizer:?}");
+        }
+    }
+
+    #[test]
+    fn refine_median_cut_with_kmeans() {
+        let pixels = gradient();
+        let options = |quantizer| QuantizeOptions {
+            quantizer,
+            max_colors: 16,
+            ..Default::default()
+        };
+
+        let median_cut = quantize(&pixels, options(Quantizer::MedianCut));
+        let kmeans = quantize(&pixels, options(Quantizer::KMeans));
+
+        assert!(mean_error(&pixels, &kmeans) <= mean_error(&pixels, &median_cut));
+    }
+
+    #[test]
+    fn share_one_palette_across_frames() {
+        let red = [[255, 0, 0, 255]; 4];
+        let blue = [
+            [0, 0, 255, 255],
+            [0, 0, 255, 255],
+            [0, 0, 0, 0],
+            [0, 0, 0, 0],
+        ];
+
+        let quantized = quantize_frames(&[&red, &blue], QuantizeOptions::default());
+
+        assert_eq!(quantized.colors.len(), 2);
+        assert_eq!(quantized.transparent_idx, Some(2));
+        assert_eq!(quantized.indices, vec![vec![0; 4], vec![1, 1, 2, 2]]);
+    }
+}
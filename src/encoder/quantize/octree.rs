@@ -0,0 +1,161 @@
+use super::WeightedColor;
+use crate::gif::Color;
+
+const DEPTH: usize = 8;
+
+/// Inserts every color into an octree indexed by the bits of its channels,
+/// then merges the deepest branches until at most `max_colors` leaves remain
+pub(crate) fn quantize(histogram: &[WeightedColor], max_colors: usize) -> Vec<Color> {
+    let mut tree = Octree::default();
+
+    for (rgb, count) in histogram {
+        tree.insert(*rgb, *count);
+    }
+
+    tree.sort_levels();
+
+    while tree.leaves > max_colors {
+        tree.reduce();
+    }
+
+    tree.colors()
+}
+
+#[derive(Default, Clone)]
+struct Node {
+    /// Child node indices, 0 is the root and marks a missing child
+    children: [u32; 8],
+    sums: [u64; 3],
+    pixels: u64,
+    /// Pixels in this node and all of its descendants
+    subtree_pixels: u64,
+    is_leaf: bool,
+}
+
+struct Octree {
+    nodes: Vec<Node>,
+    /// Inner nodes at every level, candidates for merging
+    levels: Vec<Vec<usize>>,
+    leaves: usize,
+}
+
+impl Default for Octree {
+    fn default() -> Self {
+        let mut levels = vec![Vec::new(); DEPTH];
+        // the root is merged last, leaving a single color
+        levels[0].push(0);
+
+        Self {
+            nodes: vec![Node::default()],
+            levels,
+            leaves: 0,
+        }
+    }
+}
+
+impl Octree {
+    fn insert(&mut self, rgb: [u8; 3], count: u32) {
+        let mut node = 0;
+        self.nodes[node].subtree_pixels += count as u64;
+
+        for level in 0..DEPTH {
+            let shift = 7 - level;
+            let child = rgb.iter().fold(0, |child, channel| {
+                (child << 1) | ((*channel >> shift) & 1) as usize
+            });
+
+            node = match self.nodes[node].children[child] {
+                0 => {
+                    let created = self.nodes.len();
+                    self.nodes.push(Node::default());
+                    self.nodes[node].children[child] = created as u32;
+
+                    if level + 1 < DEPTH {
+                        self.levels[level + 1].push(created);
+                    } else {
+                        self.nodes[created].is_leaf = true;
+                        self.leaves += 1;
+                    }
+
+                    created
+                }
+                existing => existing as usize,
+            };
+            self.nodes[node].subtree_pixels += count as u64;
+
+            if self.nodes[node].is_leaf {
+                break;
+            }
+        }
+
+        let leaf = &mut self.nodes[node];
+        for (sum, channel) in leaf.sums.iter_mut().zip(rgb) {
+            *sum += channel as u64 * count as u64;
+        }
+        leaf.pixels += count as u64;
+    }
+
+    /// Orders the inner nodes of every level from most to least populated
+    fn sort_levels(&mut self) {
+        for level in &mut self.levels {
+            level.sort_unstable_by_key(|node| std::cmp::Reverse(self.nodes[*node].subtree_pixels));
+        }
+    }
+
+    /// Merges the children of the least populated node on the deepest level
+    /// that still has inner nodes
+    fn reduce(&mut self) {
+        let Some(node) = self.levels.iter_mut().rev().find_map(|level| level.pop()) else {
+            return;
+        };
+
+        let mut sums = [0u64; 3];
+        let mut pixels = 0;
+        let mut merged = 0;
+
+        for child in self.nodes[node]
+            .children
+            .into_iter()
+            .filter(|child| *child != 0)
+        {
+            let child = &self.nodes[child as usize];
+            for (sum, child_sum) in sums.iter_mut().zip(child.sums) {
+                *sum += child_sum;
+            }
+            pixels += child.pixels;
+            merged += 1;
+        }
+
+        let node = &mut self.nodes[node];
+        node.children = [0; 8];
+        node.sums = sums;
+        node.pixels = pixels;
+        node.is_leaf = true;
+        self.leaves = self.leaves + 1 - merged;
+    }
+
+    fn colors(&self) -> Vec<Color> {
+        let mut colors = Vec::with_capacity(self.leaves);
+        let mut stack = vec![0];
+
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+
+            if node.is_leaf {
+                if node.pixels > 0 {
+                    let [r, g, b] = node.sums.map(|sum| (sum / node.pixels) as u8);
+                    colors.push(Color::from_triple((r, g, b)));
+                }
+            } else {
+                stack.extend(
+                    node.children
+                        .iter()
+                        .filter(|child| **child != 0)
+                        .map(|child| *child as usize),
+                );
+            }
+        }
+
+        colors
+    }
+}
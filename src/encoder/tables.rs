@@ -0,0 +1,544 @@
+use std::collections::HashMap;
+
+use super::{lzw_encode, lzw_encode_lossy, min_code_size};
+use crate::gif::{
+    Block, Color, ImageBlock, ImageContent, Rect,
+    data::ImageData,
+    descriptor::ImageDescriptor,
+    extension::{DisposalMethod, GraphicControlExtension},
+    table::{ColorTable, GlobalColorTable, MAX_COLORS},
+};
+
+/// Frame mapped onto a color table, not yet compressed
+pub(crate) struct IndexedFrame {
+    pub(crate) rect: Rect,
+    pub(crate) delay_time: u16,
+    pub(crate) disposal: DisposalMethod,
+    pub(crate) indices: Vec<u8>,
+    pub(crate) table: ColorTable,
+    pub(crate) transparent_idx: Option<u8>,
+}
+
+impl IndexedFrame {
+    /// Frame drawing `pixels` over `rect`, `None` pixels are transparent.
+    /// Returns `None` if the pixels need more than 256 table entries.
+    pub(crate) fn from_pixels(
+        rect: Rect,
+        delay_time: u16,
+        disposal: DisposalMethod,
+        pixels: &[Option<Color>],
+    ) -> Option<Self> {
+        let mut positions: HashMap<Color, u8> = HashMap::new();
+        let mut colors = Vec::new();
+
+        for color in pixels.iter().flatten() {
+            positions.entry(*color).or_insert_with(|| {
+                colors.push(*color);
+                (colors.len() - 1) as u8
+            });
+        }
+
+        let has_transparency = pixels.iter().any(Option::is_none);
+        if colors.len() + has_transparency as usize > MAX_COLORS {
+            return None;
+        }
+
+        let transparent_idx = has_transparency.then_some(colors.len() as u8);
+        if has_transparency {
+            colors.push(Color::default());
+        }
+
+        let indices = pixels
+            .iter()
+            .map(|pixel| match pixel {
+                Some(color) => positions[color],
+                None => (colors.len() - 1) as u8,
+            })
+            .collect();
+
+        Some(Self {
+            rect,
+            delay_time,
+            disposal,
+            indices,
+            table: ColorTable::new(colors),
+            transparent_idx,
+        })
+    }
+
+    /// Image block of the frame with its table as the local color table
+    pub(crate) fn local_block(self, user_input: bool) -> Block {
+        let lct = self.table.padded();
+        let data = lzw_encode(&self.indices, min_code_size(lct.len()));
+        let mut gce =
+            GraphicControlExtension::new(self.delay_time, self.disposal, self.transparent_idx);
+        gce.set_user_input(user_input);
+        let rect = self.rect;
+        let mut descriptor = ImageDescriptor::new(rect.left, rect.top, rect.width, rect.height);
+        descriptor.set_local_color_table(Some(&lct));
+
+        Block::Image(ImageBlock {
+            gce: Some(gce),
+            content: ImageContent::image_from_tuple((descriptor, Some(lct), data)),
+        })
+    }
+}
+
+/// Frame reduced to the colors it uses, the transparent entry comes last
+struct PrunedFrame {
+    rect: Rect,
+    delay_time: u16,
+    disposal: DisposalMethod,
+    colors: Vec<Color>,
+    has_transparency: bool,
+    indices: Vec<u8>,
+}
+
+impl PrunedFrame {
+    fn new(frame: IndexedFrame) -> Self {
+        let mut used = [false; MAX_COLORS];
+        for idx in &frame.indices {
+            used[*idx as usize] = true;
+        }
+
+        let has_transparency = frame
+            .transparent_idx
+            .is_some_and(|transparent_idx| used[transparent_idx as usize]);
+
+        let table = frame.table.colors();
+        let is_opaque =
+            |idx: usize| idx < table.len() && used[idx] && Some(idx as u8) != frame.transparent_idx;
+
+        // sorted so that frames using the same colors end up with equal tables
+        let mut colors: Vec<Color> = (0..table.len())
+            .filter(|idx| is_opaque(*idx))
+            .map(|idx| table[idx])
+            .collect();
+        colors.sort_unstable_by_key(sort_key);
+        colors.dedup();
+
+        // the transparent index may lie past the table
+        let transparent_idx = colors.len() as u8;
+        let remap: Vec<u8> = (0..MAX_COLORS)
+            .map(|idx| match is_opaque(idx) {
+                true => colors
+                    .binary_search_by_key(&sort_key(&table[idx]), sort_key)
+                    .unwrap_or_default() as u8,
+                false => transparent_idx,
+            })
+            .collect();
+
+        Self {
+            rect: frame.rect,
+            delay_time: frame.delay_time,
+            disposal: frame.disposal,
+            indices: frame
+                .indices
+                .iter()
+                .map(|idx| remap[*idx as usize])
+                .collect(),
+            colors,
+            has_transparency,
+        }
+    }
+
+    fn local_table(&self) -> ColorTable {
+        let mut colors = self.colors.clone();
+        if self.has_transparency {
+            colors.push(Color::default());
+        }
+
+        ColorTable::new(colors).padded()
+    }
+
+    fn local_transparent_idx(&self) -> Option<u8> {
+        self.has_transparency.then_some(self.colors.len() as u8)
+    }
+
+    /// Indices into `global` and the transparent index, `None` if the global
+    /// table misses a color or has no entry left for transparency
+    fn global_indices(&self, global: &GlobalColorTable) -> Option<(Vec<u8>, Option<u8>)> {
+        let positions: HashMap<Color, u8> = global
+            .colors()
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(idx, color)| (*color, idx as u8))
+            .collect();
+
+        let mut remap = self
+            .colors
+            .iter()
+            .map(|color| positions.get(color).copied())
+            .collect::<Option<Vec<u8>>>()?;
+
+        let transparent_idx = match self.has_transparency {
+            true => {
+                let idx = (0..global.len())
+                    .map(|idx| idx as u8)
+                    .find(|idx| !remap.contains(idx))?;
+                remap.push(idx);
+                Some(idx)
+            }
+            false => None,
+        };
+
+        let indices = self
+            .indices
+            .iter()
+            .map(|idx| remap[*idx as usize])
+            .collect();
+
+        Some((indices, transparent_idx))
+    }
+
+    /// Compresses `indices` into `table`, which is written as the local
+    /// table when given as `lct`
+    fn block(
+        &self,
+        indices: &[u8],
+        table: &ColorTable,
+        lct: Option<ColorTable>,
+        transparent_idx: Option<u8>,
+        max_error: u16,
+    ) -> Block {
+        let data = compress(indices, table, transparent_idx, max_error);
+        let gce = GraphicControlExtension::new(self.delay_time, self.disposal, transparent_idx);
+        let rect = self.rect;
+        let mut descriptor = ImageDescriptor::new(rect.left, rect.top, rect.width, rect.height);
+        descriptor.set_local_color_table(lct.as_ref());
+
+        Block::Image(ImageBlock {
+            gce: Some(gce),
+            content: ImageContent::image_from_tuple((descriptor, lct, data)),
+        })
+    }
+}
+
+/// Gives every frame the smallest color table that holds its colors.
+///
+/// Unused entries are dropped, a local table sharing colors with `global`
+/// is only kept when it makes the frame smaller and local tables shared by
+/// several frames become the global table. Pixels may be off by
+/// `max_error`, see [`lzw_encode_lossy`].
+pub(crate) fn select_tables(
+    frames: Vec<IndexedFrame>,
+    global: Option<GlobalColorTable>,
+    max_error: u16,
+) -> (Option<GlobalColorTable>, Vec<Block>) {
+    let frames: Vec<PrunedFrame> = frames.into_iter().map(PrunedFrame::new).collect();
+
+    // only the colors in use are taken from `global`
+    let candidate = match global {
+        Some(_) => union(frames.iter()).or_else(|| most_shared(&frames)),
+        None => most_shared(&frames),
+    };
+
+    let costs: Vec<(Option<usize>, usize)> = frames
+        .iter()
+        .map(|frame| frame_costs(frame, candidate.as_ref(), max_error))
+        .collect();
+
+    let uses_global: Vec<bool> = costs
+        .iter()
+        .map(|(global, local)| global.is_some_and(|global| global <= *local))
+        .collect();
+
+    // the global table is written once and has to pay for itself
+    let with_global = candidate.as_ref().map_or(0, |global| global.len() * 3)
+        + costs
+            .iter()
+            .map(|(global, local)| global.unwrap_or(*local).min(*local))
+            .sum::<usize>();
+    let without_global: usize = costs.iter().map(|(_, local)| local).sum();
+    let uses_global = match with_global <= without_global {
+        true => uses_global,
+        false => vec![false; frames.len()],
+    };
+
+    // tables only hold the colors of the frames that use them
+    let global = union(
+        frames
+            .iter()
+            .zip(&uses_global)
+            .filter(|(_, uses)| **uses)
+            .map(|(frame, _)| frame),
+    );
+
+    let blocks = frames
+        .iter()
+        .zip(&uses_global)
+        .map(|(frame, uses_global)| {
+            let shared = global
+                .as_ref()
+                .filter(|_| *uses_global)
+                .and_then(|global| Some((global, frame.global_indices(global)?)));
+
+            match shared {
+                Some((global, (indices, transparent_idx))) => {
+                    frame.block(&indices, global, None, transparent_idx, max_error)
+                }
+                None => {
+                    let table = frame.local_table();
+                    let transparent_idx = frame.local_transparent_idx();
+                    let lct = Some(table.clone());
+                    frame.block(&frame.indices, &table, lct, transparent_idx, max_error)
+                }
+            }
+        })
+        .collect();
+
+    (global, blocks)
+}
+
+/// Bytes the frame takes with `global` and with its own table, `None` if
+/// `global` does not hold its colors
+fn frame_costs(
+    frame: &PrunedFrame,
+    global: Option<&GlobalColorTable>,
+    max_error: u16,
+) -> (Option<usize>, usize) {
+    let table = frame.local_table();
+    let local = compress(
+        &frame.indices,
+        &table,
+        frame.local_transparent_idx(),
+        max_error,
+    );
+    let local = data_len(&local) + table.len() * 3;
+
+    let global = global.and_then(|global| {
+        let (indices, transparent_idx) = frame.global_indices(global)?;
+        Some(data_len(&compress(
+            &indices,
+            global,
+            transparent_idx,
+            max_error,
+        )))
+    });
+
+    (global, local)
+}
+
+/// Compresses `indices` of `table`, lossy if `max_error` is above 0
+fn compress(
+    indices: &[u8],
+    table: &ColorTable,
+    transparent_idx: Option<u8>,
+    max_error: u16,
+) -> ImageData {
+    let min_code_size = min_code_size(table.len());
+
+    match max_error {
+        0 => lzw_encode(indices, min_code_size),
+        _ => lzw_encode_lossy(
+            indices,
+            min_code_size,
+            table.colors(),
+            transparent_idx,
+            max_error,
+        ),
+    }
+}
+
+fn data_len(data: &ImageData) -> usize {
+    data.sub_blocks.iter().map(|block| block.0.len() + 1).sum()
+}
+
+/// Table holding the colors of all `frames`, `None` if there are none or
+/// they do not fit.
+///
+/// Frames with transparency need an entry they do not draw with, the
+/// padding provides one and a spare entry is only added when there is none.
+/// A full table leaves such frames to their local tables.
+fn union<'a>(frames: impl Iterator<Item = &'a PrunedFrame>) -> Option<GlobalColorTable> {
+    let mut has_transparency = false;
+    let mut colors = Vec::new();
+    let mut any = false;
+
+    for frame in frames {
+        any = true;
+        has_transparency |= frame.has_transparency;
+        colors.extend_from_slice(&frame.colors);
+    }
+
+    colors.sort_unstable_by_key(sort_key);
+    colors.dedup();
+
+    let is_filled = colors.len() == ColorTable::new(colors.clone()).padded().len();
+    if has_transparency && is_filled && colors.len() < MAX_COLORS {
+        colors.push(Color::default());
+    }
+
+    (any && colors.len() <= MAX_COLORS).then(|| ColorTable::new(colors).padded())
+}
+
+fn sort_key(color: &Color) -> (u8, u8, u8) {
+    (color.r, color.g, color.b)
+}
+
+/// Local table used by the most frames, if any is used more than once
+fn most_shared(frames: &[PrunedFrame]) -> Option<GlobalColorTable> {
+    let mut counts: HashMap<&[Color], usize> = HashMap::new();
+    for frame in frames {
+        *counts.entry(&frame.colors).or_default() += 1;
+    }
+
+    // ties go to the first table in frame order
+    let colors = frames
+        .iter()
+        .map(|frame| (&frame.colors, counts[frame.colors.as_slice()]))
+        .filter(|(_, count)| *count > 1)
+        .rev()
+        .max_by_key(|(colors, count)| (*count, colors.len()))?
+        .0;
+
+    union(frames.iter().filter(|frame| frame.colors == *colors))
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+
+    const RED: Color = Color { r: 255, g: 0, b: 0 };
+    const BLUE: Color = Color { r: 0, g: 0, b: 255 };
+
+    fn frame(indices: Vec<u8>, colors: Vec<Color>, transparent_idx: Option<u8>) -> IndexedFrame {
+        IndexedFrame {
+            rect: Rect::new(0, 0, indices.len() as u16, 1),
+            delay_time: 0,
+            disposal: DisposalMethod::Keep,
+            indices,
+            table: ColorTable::new(colors),
+            transparent_idx,
+        }
+    }
+
+    fn lct_len(block: &Block) -> Option<usize> {
+        match block {
+            Block::Image(ImageBlock {
+                content: ImageContent::Image(image),
+                ..
+            }) => image.lct.as_ref().map(ColorTable::len),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn drop_unused_entries() {
+        let mut colors = vec![Color::default(); 200];
+        colors[7] = RED;
+        colors[150] = BLUE;
+
+        let (gct, blocks) = select_tables(vec![frame(vec![7, 150, 7], colors, Some(3))], None, 0);
+
+        assert!(gct.is_none());
+        assert_eq!(lct_len(&blocks[0]), Some(2));
+        let Block::Image(ImageBlock { gce: Some(gce), .. }) = &blocks[0] else {
+            panic!("expected an image");
+        };
+        assert_eq!(gce.transparent_color_idx(), None);
+    }
+
+    #[test]
+    fn promote_shared_local_tables() {
+        let frames = vec![
+            frame(vec![0, 1], vec![RED, BLUE], None),
+            frame(vec![1, 0, 2], vec![BLUE, RED, Color::default()], Some(2)),
+            frame(vec![0, 0], vec![RED, BLUE], None),
+        ];
+
+        let (gct, blocks) = select_tables(frames, None, 0);
+
+        assert_eq!(
+            gct.map(|gct| gct.colors().to_vec()),
+            Some(vec![BLUE, RED, Color::default(), Color::default()])
+        );
+        assert_eq!(
+            blocks.iter().map(lct_len).collect::<Vec<_>>(),
+            vec![None, None, None]
+        );
+    }
+
+    #[test]
+    fn promote_local_tables_shared_by_frames() {
+        let grays: Vec<Color> = (0..200)
+            .map(|idx| Color::from_triple((idx, idx, idx)))
+            .collect();
+        let reds: Vec<Color> = (0..200)
+            .map(|idx| Color::from_triple((idx, 0, 0)))
+            .collect();
+        let all: Vec<u8> = (0..200).collect();
+        let frames = vec![
+            frame(all.clone(), grays.clone(), None),
+            frame(all.clone(), reds, None),
+            frame(all, grays.clone(), None),
+        ];
+
+        let (gct, blocks) = select_tables(frames, None, 0);
+
+        let mut expected = grays;
+        expected.resize(256, Color::default());
+        assert_eq!(gct.map(|gct| gct.colors().to_vec()), Some(expected));
+        assert_eq!(
+            blocks.iter().map(lct_len).collect::<Vec<_>>(),
+            vec![None, Some(256), None]
+        );
+    }
+
+    #[test]
+    fn keep_small_local_tables_next_to_a_large_global() {
+        let many: Vec<Color> = (0..=255)
+            .map(|idx| Color::from_triple((idx, idx, idx)))
+            .collect();
+        let noise: Vec<u8> = (0..4096u32)
+            .map(|idx| (idx.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect();
+        let frames = vec![
+            frame(noise, many.clone(), None),
+            frame([0, 1].repeat(2048), many.clone(), None),
+        ];
+
+        let (gct, blocks) = select_tables(frames, Some(ColorTable::new(many)), 0);
+
+        assert_eq!(gct.map(|gct| gct.len()), Some(256));
+        assert_eq!(lct_len(&blocks[0]), None);
+        assert_eq!(lct_len(&blocks[1]), Some(2));
+    }
+
+    #[test]
+    fn find_a_transparent_entry_in_a_full_global() {
+        let many: Vec<Color> = (0..=255)
+            .map(|idx| Color::from_triple((idx, idx, idx)))
+            .collect();
+        let all: Vec<u8> = (0..=255).collect();
+        let frames = vec![
+            frame(all.clone(), many.clone(), None),
+            frame(all.clone(), many.clone(), None),
+            frame(all[1..].to_vec(), many, Some(255)),
+        ];
+
+        let (gct, blocks) = select_tables(frames, None, 0);
+
+        assert_eq!(gct.map(|gct| gct.len()), Some(256));
+        assert_eq!(
+            blocks.iter().map(lct_len).collect::<Vec<_>>(),
+            vec![None, None, None]
+        );
+    }
+
+    #[test]
+    fn fill_a_table_with_transparency() {
+        let pixels: Vec<Option<Color>> = (0..=255)
+            .map(|idx| (idx != 0).then(|| Color::from_triple((idx as u8, 0, 0))))
+            .collect();
+
+        let frame =
+            IndexedFrame::from_pixels(Rect::new(0, 0, 16, 16), 0, DisposalMethod::Keep, &pixels)
+                .unwrap();
+
+        assert_eq!(frame.table.len(), 256);
+        assert_eq!(frame.transparent_idx, Some(255));
+        assert_eq!(frame.indices[0], 255);
+    }
+}
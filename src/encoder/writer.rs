@@ -0,0 +1,283 @@
+use std::io::{BufWriter, Write};
+
+use super::EncodeError;
+use crate::gif::{
+    Block, Gif, GifVersion, ImageBlock, ImageContent, SubBlock,
+    data::MAX_SUB_BLOCK_LEN,
+    descriptor::{ImageDescriptor, LogicalScreenDescriptor},
+    extension::{
+        ApplicationExtension, CommentExtension, GraphicControlExtension, NetScapeExtension,
+        PlainTextExtension,
+    },
+    table::{ColorTable, MAX_COLORS},
+};
+
+const EXTENSION_INTRODUCER: u8 = 0x21;
+const GRAPHIC_CONTROL_LABEL: u8 = 0xF9;
+const PLAIN_TEXT_LABEL: u8 = 0x01;
+const APPLICATION_EXTENSION_LABEL: u8 = 0xFF;
+const COMMENT_EXTENSION_LABEL: u8 = 0xFE;
+const APPLICATION_BLOCK_SIZE: u8 = 0x0B;
+const IMAGE_SEPARATOR: u8 = 0x2C;
+const BLOCK_TERMINATOR: u8 = 0x00;
+const TRAILER: u8 = 0x3B;
+
+impl Gif {
+    /// Serializes the GIF, blocks are written exactly as they are stored
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), EncodeError> {
+        let mut writer = GifWriter {
+            out: BufWriter::new(writer),
+        };
+
+        writer.gif(self)?;
+        writer.out.flush()?;
+
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    /// Size of the serialized GIF in bytes, without keeping the bytes around
+    pub fn encoded_len(&self) -> Result<usize, EncodeError> {
+        let mut counter = ByteCounter(0);
+        self.write_to(&mut counter)?;
+
+        Ok(counter.0)
+    }
+}
+
+/// Sink that only counts what is written to it
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+struct GifWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> GifWriter<W> {
+    fn gif(&mut self, gif: &Gif) -> Result<(), EncodeError> {
+        self.header(&gif.version)?;
+        self.logical_screen_descriptor(&gif.lsd)?;
+
+        if let Some(gct) = &gif.gct {
+            self.color_table(gct)?;
+        }
+
+        for block in &gif.blocks {
+            self.block(block)?;
+        }
+
+        self.bytes(&[TRAILER])
+    }
+
+    fn header(&mut self, version: &GifVersion) -> Result<(), EncodeError> {
+        let version: &[u8; 3] = match version {
+            GifVersion::V89a => b"89a",
+        };
+
+        self.bytes(b"GIF")?;
+        self.bytes(version)
+    }
+
+    fn logical_screen_descriptor(
+        &mut self,
+        lsd: &LogicalScreenDescriptor,
+    ) -> Result<(), EncodeError> {
+        self.u16(lsd.canvas_width)?;
+        self.u16(lsd.canvas_height)?;
+        self.bytes(&[
+            lsd.flags.raw,
+            lsd.background_color_idx,
+            lsd.pixel_aspect_ratio,
+        ])
+    }
+
+    fn color_table(&mut self, table: &ColorTable) -> Result<(), EncodeError> {
+        if table.colors().len() > MAX_COLORS {
+            return Err(EncodeError::InvalidData(
+                format!("color table has {} colors", table.colors().len()).into(),
+            ));
+        }
+
+        for color in table.colors() {
+            self.bytes(&[color.r, color.g, color.b])?;
+        }
+
+        Ok(())
+    }
+
+    fn block(&mut self, block: &Block) -> Result<(), EncodeError> {
+        match block {
+            Block::Image(image_block) => self.image_block(image_block),
+            Block::ApplicationExtension(extension) => self.application_extension(extension),
+            Block::CommentExtension(extension) => self.comment_extension(extension),
+        }
+    }
+
+    fn image_block(&mut self, block: &ImageBlock) -> Result<(), EncodeError> {
+        if let Some(gce) = &block.gce {
+            self.graphic_control_extension(gce)?;
+        }
+
+        match &block.content {
+            ImageContent::Image(image) => {
+                self.image_descriptor(&image.descriptor)?;
+
+                if let Some(lct) = &image.lct {
+                    self.color_table(lct)?;
+                }
+
+                self.bytes(&[image.data.lzw_min_code_size])?;
+                self.sub_blocks(&image.data.sub_blocks)
+            }
+            ImageContent::PlainText(extension) => self.plain_text_extension(extension),
+        }
+    }
+
+    fn graphic_control_extension(
+        &mut self,
+        gce: &GraphicControlExtension,
+    ) -> Result<(), EncodeError> {
+        self.bytes(&[
+            EXTENSION_INTRODUCER,
+            GRAPHIC_CONTROL_LABEL,
+            gce.block_size(),
+            gce.packed_field(),
+        ])?;
+        self.u16(gce.delay_time())?;
+        // the raw index is kept even when the transparency flag is unset
+        self.bytes(&[gce.transparent_color_idx_raw(), BLOCK_TERMINATOR])
+    }
+
+    fn image_descriptor(&mut self, descriptor: &ImageDescriptor) -> Result<(), EncodeError> {
+        self.bytes(&[IMAGE_SEPARATOR])?;
+        self.u16(descriptor.left)?;
+        self.u16(descriptor.top)?;
+        self.u16(descriptor.width)?;
+        self.u16(descriptor.height)?;
+        self.bytes(&[descriptor.packed_byte])
+    }
+
+    fn plain_text_extension(&mut self, extension: &PlainTextExtension) -> Result<(), EncodeError> {
+        self.bytes(&[EXTENSION_INTRODUCER, PLAIN_TEXT_LABEL])?;
+        self.sub_block(&extension.header)?;
+        self.sub_blocks(&extension.sub_blocks)
+    }
+
+    fn application_extension(
+        &mut self,
+        extension: &ApplicationExtension,
+    ) -> Result<(), EncodeError> {
+        self.bytes(&[
+            EXTENSION_INTRODUCER,
+            APPLICATION_EXTENSION_LABEL,
+            APPLICATION_BLOCK_SIZE,
+        ])?;
+
+        match extension {
+            ApplicationExtension::NetScape(netscape) => {
+                self.bytes(NetScapeExtension::IDENTIFIER)?;
+                self.sub_blocks(&netscape.sub_blocks)
+            }
+            ApplicationExtension::Other(other) => {
+                self.bytes(&other.identifier)?;
+                self.bytes(&other.authentication_code)?;
+                self.sub_blocks(&other.sub_blocks)
+            }
+        }
+    }
+
+    fn comment_extension(&mut self, extension: &CommentExtension) -> Result<(), EncodeError> {
+        self.bytes(&[EXTENSION_INTRODUCER, COMMENT_EXTENSION_LABEL])?;
+        self.sub_blocks(&extension.sub_blocks)
+    }
+
+    /// Writes sub blocks followed by the block terminator
+    fn sub_blocks(&mut self, sub_blocks: &[SubBlock]) -> Result<(), EncodeError> {
+        for sub_block in sub_blocks {
+            if sub_block.0.is_empty() {
+                return Err(EncodeError::InvalidData(
+                    "empty sub block would terminate the block early".into(),
+                ));
+            }
+
+            self.sub_block(&sub_block.0)?;
+        }
+
+        self.bytes(&[BLOCK_TERMINATOR])
+    }
+
+    fn sub_block(&mut self, bytes: &[u8]) -> Result<(), EncodeError> {
+        if bytes.len() > MAX_SUB_BLOCK_LEN {
+            return Err(EncodeError::InvalidData(
+                format!("sub block of {} bytes is too long", bytes.len()).into(),
+            ));
+        }
+
+        self.bytes(&[bytes.len() as u8])?;
+        self.bytes(bytes)
+    }
+
+    fn u16(&mut self, value: u16) -> Result<(), EncodeError> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> Result<(), EncodeError> {
+        self.out.write_all(bytes)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use crate::decoder::parse;
+
+    const EARTH: &[u8] = include_bytes!("../../images/earth.gif");
+
+    fn round_trip(bytes: &[u8]) {
+        let gif = parse(bytes).unwrap();
+
+        assert_eq!(gif.to_bytes().unwrap(), bytes);
+        assert_eq!(gif.encoded_len().unwrap(), bytes.len());
+    }
+
+    #[test]
+    fn write_parsed_gif_unchanged() {
+        round_trip(EARTH);
+    }
+
+    #[test]
+    fn write_every_extension_unchanged() {
+        let mut bytes = Vec::new();
+        // header, 2x2 canvas and 2 color global table
+        bytes.extend(b"GIF89a\x02\x00\x02\x00\x80\x00\x00\x00\x00\x00\xFF\xFF\xFF");
+        // looping netscape and unknown application extensions
+        bytes.extend(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00");
+        bytes.extend(b"\x21\xFF\x0BXMP DataXMP\x02ab\x00");
+        bytes.extend(b"\x21\xFE\x05hello\x00");
+        // plain text with a graphic control extension
+        bytes.extend(b"\x21\xF9\x04\x00\x05\x00\x00\x00");
+        bytes.extend(b"\x21\x01\x0C\x00\x00\x00\x00\x02\x00\x02\x00\x01\x01\x01\x00\x02hi\x00");
+        // image without graphic control extension
+        bytes.extend(b"\x2C\x00\x00\x00\x00\x02\x00\x02\x00\x00\x02\x02\x44\x01\x00");
+        bytes.extend(b"\x3B");
+
+        round_trip(&bytes);
+    }
+}
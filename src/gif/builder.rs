@@ -0,0 +1,339 @@
+use thiserror::Error;
+
+use super::{
+    Block, Color, Gif, GifVersion, ImageBlock, ImageContent, Rect, SubBlock,
+    descriptor::{ImageDescriptor, LogicalScreenDescriptor},
+    extension::{
+        ApplicationExtension, CommentExtension, DisposalMethod, GraphicControlExtension,
+        NetScapeExtension,
+    },
+    table::{ColorTable, MAX_COLORS},
+};
+use crate::encoder::{lzw_encode, min_code_size};
+
+/// Why a [`GifBuilder`] could not assemble its GIF
+#[derive(Debug, Error)]
+pub enum BuildError {
+    #[error("color tables need 1 to {MAX_COLORS} colors, got {0}")]
+    ColorTableLen(usize),
+    #[error("background color index {0} is outside the global color table")]
+    BackgroundOutsideTable(u8),
+    #[error("frame {frame_idx}: frames need at least one pixel")]
+    EmptyFrame { frame_idx: usize },
+    #[error("frame {frame_idx}: {rect:?} does not fit the {width}x{height} canvas")]
+    OutsideCanvas {
+        frame_idx: usize,
+        rect: Rect,
+        width: u16,
+        height: u16,
+    },
+    #[error("frame {frame_idx}: has {len} indices, expected {expected}")]
+    IndexCount {
+        frame_idx: usize,
+        len: usize,
+        expected: usize,
+    },
+    #[error("frame {frame_idx}: there is neither a local nor a global color table")]
+    MissingColorTable { frame_idx: usize },
+    #[error("frame {frame_idx}: index {idx} is outside the color table")]
+    IndexOutsideTable { frame_idx: usize, idx: u8 },
+    #[error("frame {frame_idx}: transparent index {idx} is outside the color table")]
+    TransparentIndexOutsideTable { frame_idx: usize, idx: u8 },
+}
+
+/// Assembles a [`Gif`] from indexed frames, checking that frames fit the
+/// canvas and their color tables before anything is written
+#[derive(Debug, Clone, Default)]
+pub struct GifBuilder {
+    width: u16,
+    height: u16,
+    palette: Option<Vec<Color>>,
+    background_color_idx: u8,
+    loop_count: Option<u16>,
+    comments: Vec<Vec<u8>>,
+    frames: Vec<FrameBuilder>,
+}
+
+/// One image of a [`GifBuilder`], color table indices in row-major order
+#[derive(Debug, Clone)]
+pub struct FrameBuilder {
+    rect: Rect,
+    indices: Vec<u8>,
+    palette: Option<Vec<Color>>,
+    delay_time: u16,
+    disposal: DisposalMethod,
+    transparent_idx: Option<u8>,
+}
+
+impl GifBuilder {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            ..Self::default()
+        }
+    }
+
+    /// Global color table used by frames without a palette of their own
+    pub fn global_palette(&mut self, colors: Vec<Color>) -> &mut Self {
+        self.palette = Some(colors);
+        self
+    }
+
+    /// Global color table entry of the background
+    pub fn background_color_idx(&mut self, idx: u8) -> &mut Self {
+        self.background_color_idx = idx;
+        self
+    }
+
+    /// Repeats the animation `loop_count` times, 0 loops forever
+    pub fn loop_count(&mut self, loop_count: u16) -> &mut Self {
+        self.loop_count = Some(loop_count);
+        self
+    }
+
+    /// Adds a comment extension in front of the frames
+    pub fn comment(&mut self, text: impl Into<Vec<u8>>) -> &mut Self {
+        self.comments.push(text.into());
+        self
+    }
+
+    /// Adds `frame` after the frames added so far
+    pub fn frame(&mut self, frame: &FrameBuilder) -> &mut Self {
+        self.frames.push(frame.clone());
+        self
+    }
+
+    pub fn build(&self) -> Result<Gif, BuildError> {
+        let gct = self.palette.as_deref().map(color_table).transpose()?;
+
+        if let Some(gct) = &self.palette
+            && self.background_color_idx as usize >= gct.len()
+        {
+            return Err(BuildError::BackgroundOutsideTable(
+                self.background_color_idx,
+            ));
+        }
+
+        let mut blocks = Vec::with_capacity(self.frames.len() + self.comments.len() + 1);
+
+        if let Some(loop_count) = self.loop_count {
+            blocks.push(Block::ApplicationExtension(ApplicationExtension::NetScape(
+                NetScapeExtension::with_loop_count(loop_count),
+            )));
+        }
+
+        for comment in &self.comments {
+            let sub_blocks = SubBlock::chunks(comment);
+            blocks.push(Block::CommentExtension(CommentExtension::new(sub_blocks)));
+        }
+
+        for (frame_idx, frame) in self.frames.iter().enumerate() {
+            blocks.push(frame.build(
+                frame_idx,
+                self.width,
+                self.height,
+                self.palette.as_deref(),
+            )?);
+        }
+
+        let lsd = LogicalScreenDescriptor::new(
+            self.width,
+            self.height,
+            gct.as_ref(),
+            self.background_color_idx,
+        );
+
+        Ok(Gif::from_tuple((GifVersion::V89a, lsd, gct, blocks)))
+    }
+}
+
+impl FrameBuilder {
+    /// Frame of `width * height` color table indices placed at the top left
+    /// corner
+    pub fn new(width: u16, height: u16, indices: Vec<u8>) -> Self {
+        Self {
+            rect: Rect::new(0, 0, width, height),
+            indices,
+            palette: None,
+            delay_time: 0,
+            disposal: DisposalMethod::Unspecified,
+            transparent_idx: None,
+        }
+    }
+
+    /// Offset of the frame on the canvas
+    pub fn position(&mut self, left: u16, top: u16) -> &mut Self {
+        self.rect.left = left;
+        self.rect.top = top;
+        self
+    }
+
+    /// Delay in hundredths of a second
+    pub fn delay_time(&mut self, delay_time: u16) -> &mut Self {
+        self.delay_time = delay_time;
+        self
+    }
+
+    pub fn disposal(&mut self, disposal: DisposalMethod) -> &mut Self {
+        self.disposal = disposal;
+        self
+    }
+
+    /// Color table index that is not drawn
+    pub fn transparent_idx(&mut self, idx: u8) -> &mut Self {
+        self.transparent_idx = Some(idx);
+        self
+    }
+
+    /// Local color table, used instead of the global one
+    pub fn local_palette(&mut self, colors: Vec<Color>) -> &mut Self {
+        self.palette = Some(colors);
+        self
+    }
+
+    fn build(
+        &self,
+        frame_idx: usize,
+        width: u16,
+        height: u16,
+        global: Option<&[Color]>,
+    ) -> Result<Block, BuildError> {
+        let rect = self.rect;
+        if rect.is_empty() {
+            return Err(BuildError::EmptyFrame { frame_idx });
+        }
+        if rect.right() > width as u32 || rect.bottom() > height as u32 {
+            return Err(BuildError::OutsideCanvas {
+                frame_idx,
+                rect,
+                width,
+                height,
+            });
+        }
+        let expected = rect.width as usize * rect.height as usize;
+        if self.indices.len() != expected {
+            return Err(BuildError::IndexCount {
+                frame_idx,
+                len: self.indices.len(),
+                expected,
+            });
+        }
+
+        let colors = self
+            .palette
+            .as_deref()
+            .or(global)
+            .ok_or(BuildError::MissingColorTable { frame_idx })?;
+        let table = color_table(colors)?;
+
+        if let Some(idx) = self
+            .indices
+            .iter()
+            .find(|idx| **idx as usize >= colors.len())
+        {
+            return Err(BuildError::IndexOutsideTable {
+                frame_idx,
+                idx: *idx,
+            });
+        }
+        if let Some(idx) = self
+            .transparent_idx
+            .filter(|idx| *idx as usize >= colors.len())
+        {
+            return Err(BuildError::TransparentIndexOutsideTable { frame_idx, idx });
+        }
+
+        let gce =
+            GraphicControlExtension::new(self.delay_time, self.disposal, self.transparent_idx);
+        let lct = self.palette.is_some().then_some(table.clone());
+        let mut descriptor = ImageDescriptor::new(rect.left, rect.top, rect.width, rect.height);
+        descriptor.set_local_color_table(lct.as_ref());
+        let data = lzw_encode(&self.indices, min_code_size(table.len()));
+
+        Ok(Block::Image(ImageBlock {
+            gce: Some(gce),
+            content: ImageContent::image_from_tuple((descriptor, lct, data)),
+        }))
+    }
+}
+
+/// Color table padded to a power of two
+fn color_table(colors: &[Color]) -> Result<ColorTable, BuildError> {
+    match colors.len() {
+        len if len == 0 || len > MAX_COLORS => Err(BuildError::ColorTableLen(len)),
+        _ => Ok(ColorTable::new(colors.to_vec()).padded()),
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::decoder::{composite, parse};
+
+    const RED: Color = Color { r: 255, g: 0, b: 0 };
+    const BLUE: Color = Color { r: 0, g: 0, b: 255 };
+    const GREEN: Color = Color { r: 0, g: 255, b: 0 };
+
+    #[test]
+    fn build_a_consistent_gif() {
+        let gif = GifBuilder::new(2, 2)
+            .global_palette(vec![RED, BLUE])
+            .loop_count(0)
+            .comment("built by hand")
+            .frame(
+                FrameBuilder::new(2, 2, vec![0, 1, 1, 0])
+                    .delay_time(10)
+                    .disposal(DisposalMethod::Keep),
+            )
+            .frame(
+                FrameBuilder::new(1, 1, vec![2])
+                    .position(1, 1)
+                    .local_palette(vec![RED, BLUE, GREEN])
+                    .transparent_idx(0),
+            )
+            .build()
+            .unwrap();
+
+        let gif = parse(gif.to_bytes().unwrap().as_slice()).unwrap();
+        assert!(gif.lsd.flags.global_color_table);
+        let images: Vec<_> = gif.images().map(|(_, _, image)| image).collect();
+        assert!(!images[0].descriptor.has_local_color_table());
+        assert!(images[1].descriptor.has_local_color_table());
+        assert_eq!(images[1].lct.as_ref().map(ColorTable::len), Some(4));
+        assert_eq!(images[1].descriptor.color_table_size(), 4);
+
+        let frames = composite(&gif).unwrap();
+        assert_eq!(
+            frames[1].pixels,
+            vec![
+                [255, 0, 0, 255],
+                [0, 0, 255, 255],
+                [0, 0, 255, 255],
+                [0, 255, 0, 255]
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_inconsistent_frames() {
+        let build = |frame: &FrameBuilder| {
+            GifBuilder::new(4, 4)
+                .global_palette(vec![RED, BLUE])
+                .frame(frame)
+                .build()
+        };
+
+        assert!(build(&FrameBuilder::new(2, 2, vec![0; 3])).is_err());
+        assert!(build(&FrameBuilder::new(2, 2, vec![0, 0, 0, 2])).is_err());
+        assert!(build(FrameBuilder::new(2, 2, vec![0; 4]).position(3, 0)).is_err());
+        assert!(build(FrameBuilder::new(2, 2, vec![0; 4]).transparent_idx(5)).is_err());
+        assert!(build(&FrameBuilder::new(2, 2, vec![0; 4])).is_ok());
+        assert!(
+            GifBuilder::new(2, 2)
+                .frame(&FrameBuilder::new(1, 1, vec![0]))
+                .build()
+                .is_err()
+        );
+    }
+}
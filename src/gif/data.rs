@@ -1,5 +1,8 @@
 use std::fmt::Debug;
 
+/// Sub blocks hold at most 255 bytes
+pub(crate) const MAX_SUB_BLOCK_LEN: usize = 255;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum GifVersion {
     V89a,
@@ -26,7 +29,91 @@ impl ImageData {
 #[derive(Debug, Clone, PartialEq)]
 pub struct SubBlock(pub Vec<u8>);
 
-#[derive(Clone)]
+impl SubBlock {
+    /// Splits `bytes` into as few sub blocks as possible
+    pub fn chunks(bytes: &[u8]) -> Vec<SubBlock> {
+        bytes
+            .chunks(MAX_SUB_BLOCK_LEN)
+            .map(|chunk| SubBlock(chunk.to_vec()))
+            .collect()
+    }
+}
+
+/// Red, green, blue and alpha
+pub type Rgba = [u8; 4];
+
+/// Area of the logical screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub left: u16,
+    pub top: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    pub fn new(left: u16, top: u16, width: u16, height: u16) -> Self {
+        Self {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// First column past the rectangle
+    pub fn right(&self) -> u32 {
+        self.left as u32 + self.width as u32
+    }
+
+    /// First row past the rectangle
+    pub fn bottom(&self) -> u32 {
+        self.top as u32 + self.height as u32
+    }
+
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        (self.left as u32..self.right()).contains(&x)
+            && (self.top as u32..self.bottom()).contains(&y)
+    }
+
+    /// Smallest rectangle containing both
+    pub fn union(&self, other: &Rect) -> Rect {
+        let left = self.left.min(other.left);
+        let top = self.top.min(other.top);
+        let right = self.right().max(other.right());
+        let bottom = self.bottom().max(other.bottom());
+
+        Rect::new(
+            left,
+            top,
+            (right - left as u32) as u16,
+            (bottom - top as u32) as u16,
+        )
+    }
+
+    /// Overlapping area, `None` if the rectangles do not overlap
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let left = self.left.max(other.left);
+        let top = self.top.max(other.top);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+
+        ((left as u32) < right && (top as u32) < bottom).then(|| {
+            Rect::new(
+                left,
+                top,
+                (right - left as u32) as u16,
+                (bottom - top as u32) as u16,
+            )
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -1,3 +1,5 @@
+use super::{Rect, table::ColorTable};
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct LogicalScreenDescriptor {
     pub canvas_width: u16,
@@ -26,6 +28,36 @@ impl LogicalScreenDescriptor {
             pixel_aspect_ratio,
         }
     }
+
+    /// Descriptor for a canvas with 8 bit color resolution and square pixels
+    pub fn new(
+        canvas_width: u16,
+        canvas_height: u16,
+        gct: Option<&ColorTable>,
+        background_color_idx: u8,
+    ) -> Self {
+        let mut lsd = Self::from_tuple((
+            canvas_width,
+            canvas_height,
+            Flags::COLOR_RES_MASK,
+            background_color_idx,
+            0,
+        ));
+        lsd.set_global_color_table(gct);
+
+        lsd
+    }
+
+    /// Updates the flags to describe `gct`
+    pub fn set_global_color_table(&mut self, gct: Option<&ColorTable>) {
+        let keep = self.flags.raw & (Flags::COLOR_RES_MASK | Flags::SORT_MASK);
+        let table = match gct {
+            Some(gct) => Flags::GLOBAL_COLOR_TABLE_MASK | gct.size_field(),
+            None => 0,
+        };
+
+        self.flags = (keep | table).into();
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -79,6 +111,12 @@ impl ImageDescriptor {
         (self.packed_byte & ImageDescriptor::LOCAL_COLOR_TABLE_MASK) != 0
     }
 
+    const INTERLACE_MASK: u8 = 0b0100_0000;
+
+    pub fn is_interlaced(&self) -> bool {
+        (self.packed_byte & ImageDescriptor::INTERLACE_MASK) != 0
+    }
+
     const COLOR_TABLE_SIZE_MASK: u8 = 0b0000_0111;
 
     pub fn color_table_size(&self) -> u32 {
@@ -97,4 +135,29 @@ impl ImageDescriptor {
             packed_byte,
         }
     }
+
+    /// Non interlaced descriptor without a local color table
+    pub fn new(left: u16, top: u16, width: u16, height: u16) -> Self {
+        Self::from_tuple((left, top, width, height, 0))
+    }
+
+    /// Updates the packed byte to describe `lct`
+    pub fn set_local_color_table(&mut self, lct: Option<&ColorTable>) {
+        let keep = self.packed_byte
+            & !(ImageDescriptor::LOCAL_COLOR_TABLE_MASK | ImageDescriptor::COLOR_TABLE_SIZE_MASK);
+        let table = match lct {
+            Some(lct) => ImageDescriptor::LOCAL_COLOR_TABLE_MASK | lct.size_field(),
+            None => 0,
+        };
+
+        self.packed_byte = keep | table;
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.left, self.top, self.width, self.height)
+    }
+
+    pub fn pixel_count(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
 }
@@ -0,0 +1,213 @@
+use super::{
+    Block, Gif, ImageBlock, ImageContent, SubBlock,
+    extension::{
+        ApplicationExtension, CommentExtension, DisposalMethod, GraphicControlExtension,
+        NetScapeExtension,
+    },
+};
+
+/// Edits that only touch extension blocks, image data is kept as it was read
+impl Gif {
+    /// Loop count of the NETSCAPE extension, 0 loops forever and `None` plays
+    /// the animation once
+    pub fn loop_count(&self) -> Option<u16> {
+        self.blocks.iter().find_map(|block| match block {
+            Block::ApplicationExtension(ApplicationExtension::NetScape(netscape)) => {
+                netscape.loop_count()
+            }
+            _ => None,
+        })
+    }
+
+    /// Sets the loop count in the NETSCAPE extension where it is, adding
+    /// one in front of the frames if there is none. `None` removes the loop
+    /// count and the extension if nothing else is left in it.
+    pub fn set_loop_count(&mut self, loop_count: Option<u16>) {
+        let mut found = false;
+
+        self.blocks.retain_mut(|block| match block {
+            Block::ApplicationExtension(ApplicationExtension::NetScape(netscape)) => {
+                netscape.set_loop_count(loop_count);
+                found = true;
+                !netscape.sub_blocks.is_empty()
+            }
+            _ => true,
+        });
+
+        if let (false, Some(loop_count)) = (found, loop_count) {
+            self.blocks.insert(
+                0,
+                Block::ApplicationExtension(ApplicationExtension::NetScape(
+                    NetScapeExtension::with_loop_count(loop_count),
+                )),
+            );
+        }
+    }
+
+    /// Graphic control of an image frame, `None` if the frame has none
+    pub fn graphic_control_mut(
+        &mut self,
+        frame_idx: usize,
+    ) -> Option<&mut GraphicControlExtension> {
+        self.image_blocks_mut()
+            .nth(frame_idx)
+            .and_then(|image| image.gce.as_mut())
+    }
+
+    /// Graphic control of every image frame in order, frames without one get
+    /// a default extension first
+    pub fn graphic_controls_or_default(
+        &mut self,
+    ) -> impl Iterator<Item = &mut GraphicControlExtension> {
+        self.image_blocks_mut().map(|image| {
+            image.gce.get_or_insert_with(|| {
+                GraphicControlExtension::new(0, DisposalMethod::Unspecified, None)
+            })
+        })
+    }
+
+    /// Sets the delay of every frame in hundredths of a second
+    pub fn set_delay_times(&mut self, delay_time: u16) {
+        for gce in self.graphic_controls_or_default() {
+            gce.set_delay_time(delay_time);
+        }
+    }
+
+    /// Text of every comment extension
+    pub fn comments(&self) -> impl Iterator<Item = Vec<u8>> {
+        self.blocks.iter().filter_map(|block| match block {
+            Block::CommentExtension(comment) => Some(comment.text()),
+            _ => None,
+        })
+    }
+
+    /// Adds a comment extension after the last block
+    pub fn add_comment(&mut self, text: impl AsRef<[u8]>) {
+        let sub_blocks = SubBlock::chunks(text.as_ref());
+
+        self.blocks
+            .push(Block::CommentExtension(CommentExtension::new(sub_blocks)));
+    }
+
+    fn image_blocks_mut(&mut self) -> impl Iterator<Item = &mut ImageBlock> {
+        self.blocks.iter_mut().filter_map(|block| match block {
+            Block::Image(
+                image @ ImageBlock {
+                    content: ImageContent::Image(_),
+                    ..
+                },
+            ) => Some(image),
+            _ => None,
+        })
+    }
+
+    pub fn remove_comments(&mut self) {
+        self.blocks
+            .retain(|block| !matches!(block, Block::CommentExtension(_)));
+    }
+
+    /// Removes application extensions other than the NETSCAPE loop extension,
+    /// such as XMP metadata or ICC profiles
+    pub fn strip_application_extensions(&mut self) {
+        self.blocks.retain(|block| {
+            !matches!(
+                block,
+                Block::ApplicationExtension(ApplicationExtension::Other(_))
+            )
+        });
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::decoder::parse;
+    use crate::gif::extension::UnknownApplicationExtension;
+    use crate::transform::moving_square;
+
+    #[test]
+    fn edit_metadata_and_keep_image_data() {
+        let original = moving_square();
+        let mut gif = original.clone();
+        gif.blocks
+            .push(Block::ApplicationExtension(ApplicationExtension::Other(
+                UnknownApplicationExtension::from_tuple((
+                    *b"XMP Data",
+                    *b"XMP",
+                    vec![SubBlock(vec![1])],
+                )),
+            )));
+
+        gif.set_loop_count(Some(3));
+        gif.set_delay_times(7);
+        gif.graphic_control_mut(1).unwrap().set_delay_time(50);
+        gif.remove_comments();
+        gif.add_comment("edited");
+        gif.strip_application_extensions();
+
+        let edited = parse(gif.to_bytes().unwrap().as_slice()).unwrap();
+        assert_eq!(edited.loop_count(), Some(3));
+        assert_eq!(
+            edited.comments().collect::<Vec<_>>(),
+            vec![b"edited".to_vec()]
+        );
+        assert!(!edited.blocks.iter().any(|block| matches!(
+            block,
+            Block::ApplicationExtension(ApplicationExtension::Other(_))
+        )));
+
+        let delays: Vec<u16> = edited
+            .images()
+            .map(|(_, block, _)| block.gce.as_ref().unwrap().delay_time())
+            .collect();
+        assert_eq!(&delays[..3], &[7, 50, 7]);
+
+        for ((_, _, edited), (_, _, original)) in edited.images().zip(original.images()) {
+            assert_eq!(edited.data.sub_blocks, original.data.sub_blocks);
+        }
+    }
+
+    #[test]
+    fn remove_the_loop_extension() {
+        let mut gif = moving_square();
+
+        gif.set_loop_count(Some(0));
+        gif.set_loop_count(None);
+
+        assert_eq!(gif.loop_count(), None);
+    }
+
+    #[test]
+    fn update_the_loop_count_in_place() {
+        let mut gif = moving_square();
+        gif.set_loop_count(None);
+        let mut netscape = NetScapeExtension::with_loop_count(0);
+        netscape.sub_blocks.push(SubBlock(vec![2, 0, 16, 0, 0]));
+        gif.blocks.insert(
+            1,
+            Block::ApplicationExtension(ApplicationExtension::NetScape(netscape)),
+        );
+        let (first, _, _) = gif.images().next().unwrap();
+        let Block::Image(ImageBlock { gce, .. }) = &mut gif.blocks[first] else {
+            panic!("expected an image");
+        };
+        *gce = None;
+
+        gif.set_loop_count(Some(5));
+        assert_eq!(gif.loop_count(), Some(5));
+        gif.set_loop_count(None);
+
+        let Block::ApplicationExtension(ApplicationExtension::NetScape(netscape)) = &gif.blocks[1]
+        else {
+            panic!("expected the extension to stay in place");
+        };
+        assert_eq!(netscape.sub_blocks, vec![SubBlock(vec![2, 0, 16, 0, 0])]);
+        assert_eq!(gif.loop_count(), None);
+        assert!(gif.graphic_control_mut(0).is_none());
+        assert_eq!(
+            gif.graphic_controls_or_default().count(),
+            gif.images().count()
+        );
+        assert!(gif.graphic_control_mut(0).is_some());
+    }
+}
@@ -1,4 +1,6 @@
-use super::SubBlock;
+use std::time::Duration;
+
+use super::{SubBlock, timeline::centiseconds};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct GraphicControlExtension {
@@ -9,6 +11,10 @@ pub struct GraphicControlExtension {
 }
 
 impl GraphicControlExtension {
+    const DISPOSAL_MASK: u8 = 0b0001_1100;
+    const USER_INPUT_MASK: u8 = 0b0000_0010;
+    const TRANSPARENT_COLOR_MASK: u8 = 0b0000_0001;
+
     pub fn from_tuple(
         (block_size, packed_field, delay_time, transparent_color_idx): (u8, u8, u16, u8),
     ) -> Self {
@@ -19,11 +25,126 @@ impl GraphicControlExtension {
             transparent_color_idx,
         }
     }
+
+    const BLOCK_SIZE: u8 = 4;
+
+    pub fn new(
+        delay_time: u16,
+        disposal_method: DisposalMethod,
+        transparent_color_idx: Option<u8>,
+    ) -> Self {
+        let disposal = u8::from(disposal_method) << 2;
+        let transparency = match transparent_color_idx {
+            Some(_) => GraphicControlExtension::TRANSPARENT_COLOR_MASK,
+            None => 0,
+        };
+
+        Self {
+            block_size: GraphicControlExtension::BLOCK_SIZE,
+            packed_field: disposal | transparency,
+            delay_time,
+            transparent_color_idx: transparent_color_idx.unwrap_or(0),
+        }
+    }
+
+    /// Size of the extension block, always 4 in well formed files
+    pub fn block_size(&self) -> u8 {
+        self.block_size
+    }
+
+    pub fn packed_field(&self) -> u8 {
+        self.packed_field
+    }
+
+    /// Delay in hundredths of a second
+    pub fn delay_time(&self) -> u16 {
+        self.delay_time
+    }
+
+    pub fn set_delay_time(&mut self, delay_time: u16) {
+        self.delay_time = delay_time;
+    }
+
+    pub fn delay(&self) -> Duration {
+        centiseconds(self.delay_time)
+    }
+
+    pub fn disposal_method(&self) -> DisposalMethod {
+        ((self.packed_field & GraphicControlExtension::DISPOSAL_MASK) >> 2).into()
+    }
+
+    pub fn set_disposal_method(&mut self, disposal_method: DisposalMethod) {
+        let disposal = u8::from(disposal_method) << 2;
+        self.packed_field =
+            (self.packed_field & !GraphicControlExtension::DISPOSAL_MASK) | disposal;
+    }
+
+    pub fn user_input(&self) -> bool {
+        (self.packed_field & GraphicControlExtension::USER_INPUT_MASK) != 0
+    }
+
+    pub fn set_user_input(&mut self, user_input: bool) {
+        self.packed_field = match user_input {
+            true => self.packed_field | GraphicControlExtension::USER_INPUT_MASK,
+            false => self.packed_field & !GraphicControlExtension::USER_INPUT_MASK,
+        };
+    }
+
+    /// Returns the transparent color index if the transparency flag is set
+    pub fn transparent_color_idx(&self) -> Option<u8> {
+        let has_transparency =
+            (self.packed_field & GraphicControlExtension::TRANSPARENT_COLOR_MASK) != 0;
+
+        has_transparency.then_some(self.transparent_color_idx)
+    }
+
+    /// Transparent color index as stored, regardless of the transparency flag
+    pub fn transparent_color_idx_raw(&self) -> u8 {
+        self.transparent_color_idx
+    }
+}
+
+/// What to do with a frame once its delay has passed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisposalMethod {
+    /// No disposal specified, decoders treat this like [`DisposalMethod::Keep`]
+    #[default]
+    Unspecified,
+    /// Leave the frame in place ("do not dispose")
+    Keep,
+    /// Clear the frame area to the background
+    RestoreBackground,
+    /// Restore the frame area to what it was before the frame was drawn
+    RestorePrevious,
+}
+
+impl From<u8> for DisposalMethod {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => DisposalMethod::Keep,
+            2 => DisposalMethod::RestoreBackground,
+            3 => DisposalMethod::RestorePrevious,
+            // 4-7 are reserved
+            _ => DisposalMethod::Unspecified,
+        }
+    }
+}
+
+impl From<DisposalMethod> for u8 {
+    fn from(value: DisposalMethod) -> Self {
+        match value {
+            DisposalMethod::Unspecified => 0,
+            DisposalMethod::Keep => 1,
+            DisposalMethod::RestoreBackground => 2,
+            DisposalMethod::RestorePrevious => 3,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ApplicationExtension {
     NetScape(NetScapeExtension),
+    Other(UnknownApplicationExtension),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -32,13 +153,109 @@ pub struct NetScapeExtension {
 }
 
 impl NetScapeExtension {
+    pub const IDENTIFIER: &[u8; 11] = b"NETSCAPE2.0";
+
+    const LOOP_SUB_BLOCK_ID: u8 = 0x01;
+
     pub fn new(sub_blocks: Vec<SubBlock>) -> Self {
         Self { sub_blocks }
     }
+
+    /// Extension that repeats the animation `loop_count` times, 0 loops forever
+    pub fn with_loop_count(loop_count: u16) -> Self {
+        let [lo, hi] = loop_count.to_le_bytes();
+
+        Self::new(vec![SubBlock(vec![
+            NetScapeExtension::LOOP_SUB_BLOCK_ID,
+            lo,
+            hi,
+        ])])
+    }
+
+    /// Loop count from the looping sub block, 0 means forever
+    pub fn loop_count(&self) -> Option<u16> {
+        self.sub_blocks
+            .iter()
+            .find_map(|block| match block.0.as_slice() {
+                [NetScapeExtension::LOOP_SUB_BLOCK_ID, lo, hi, ..] => {
+                    Some(u16::from_le_bytes([*lo, *hi]))
+                }
+                _ => None,
+            })
+    }
+
+    /// Replaces the looping sub block, other sub blocks are kept. `None`
+    /// removes it.
+    pub fn set_loop_count(&mut self, loop_count: Option<u16>) {
+        let is_loop = |block: &SubBlock| {
+            matches!(
+                block.0.as_slice(),
+                [NetScapeExtension::LOOP_SUB_BLOCK_ID, _, _, ..]
+            )
+        };
+        let position = self.sub_blocks.iter().position(is_loop);
+
+        match (position, loop_count) {
+            (Some(position), Some(loop_count)) => {
+                let [lo, hi] = loop_count.to_le_bytes();
+                self.sub_blocks[position].0[1..3].copy_from_slice(&[lo, hi]);
+            }
+            (None, Some(loop_count)) => self
+                .sub_blocks
+                .extend(NetScapeExtension::with_loop_count(loop_count).sub_blocks),
+            (_, None) => self.sub_blocks.retain(|block| !is_loop(block)),
+        }
+    }
 }
 
+/// Application extension that is kept as is
 #[derive(Debug, Clone, PartialEq)]
-pub struct CommentExtension;
+pub struct UnknownApplicationExtension {
+    pub identifier: [u8; 8],
+    pub authentication_code: [u8; 3],
+    pub sub_blocks: Vec<SubBlock>,
+}
+
+impl UnknownApplicationExtension {
+    pub fn from_tuple(
+        (identifier, authentication_code, sub_blocks): ([u8; 8], [u8; 3], Vec<SubBlock>),
+    ) -> Self {
+        Self {
+            identifier,
+            authentication_code,
+            sub_blocks,
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct PlainTextExtension;
+pub struct CommentExtension {
+    pub sub_blocks: Vec<SubBlock>,
+}
+
+impl CommentExtension {
+    pub fn new(sub_blocks: Vec<SubBlock>) -> Self {
+        Self { sub_blocks }
+    }
+
+    /// Comment bytes with the sub blocks joined
+    pub fn text(&self) -> Vec<u8> {
+        self.sub_blocks
+            .iter()
+            .flat_map(|block| block.0.iter().copied())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlainTextExtension {
+    /// Text grid position, cell size and colors
+    pub header: Vec<u8>,
+    pub sub_blocks: Vec<SubBlock>,
+}
+
+impl PlainTextExtension {
+    pub fn from_tuple((header, sub_blocks): (Vec<u8>, Vec<SubBlock>)) -> Self {
+        Self { header, sub_blocks }
+    }
+}
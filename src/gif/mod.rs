@@ -1,20 +1,48 @@
+pub mod builder;
 pub mod data;
 pub mod descriptor;
+mod edit;
 pub mod extension;
 pub mod table;
+pub mod timeline;
 
 use std::fmt::{Debug, Display, Formatter};
+use std::time::Duration;
 
 pub use data::*;
 use descriptor::{ImageDescriptor, LogicalScreenDescriptor};
 use extension::{
-    ApplicationExtension, CommentExtension, GraphicControlExtension, PlainTextExtension,
+    ApplicationExtension, CommentExtension, DisposalMethod, GraphicControlExtension,
+    PlainTextExtension,
 };
 use table::{GlobalColorTable, LocalColorTable};
 
+/// A decoded image and where it is placed on the logical screen
 #[derive(Clone)]
 pub struct Frame {
+    /// Colors of the frame rectangle in row-major order
     pub pixels: Vec<Color>,
+    /// Color table indices of the frame rectangle in row-major order
+    pub indices: Vec<u8>,
+    pub left: u16,
+    pub top: u16,
+    pub width: u16,
+    pub height: u16,
+    pub delay: Duration,
+    pub disposal: DisposalMethod,
+    /// Pixels with this index are not drawn
+    pub transparent_color_idx: Option<u8>,
+    /// Index of the source block in [`Gif::blocks`]
+    pub block_idx: usize,
+    /// When the frame is shown, relative to the start of the animation
+    pub start: Duration,
+}
+
+impl Frame {
+    pub fn is_transparent(&self, pixel_idx: usize) -> bool {
+        self.transparent_color_idx
+            .is_some_and(|transparent| self.indices.get(pixel_idx) == Some(&transparent))
+    }
 }
 
 /// Represents a parsed GIF
@@ -61,6 +89,22 @@ impl Gif {
             blocks,
         }
     }
+
+    /// Image frames in display order, with the index of their block
+    pub fn images(&self) -> impl Iterator<Item = (usize, &ImageBlock, &Image)> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(block_idx, block)| match block {
+                Block::Image(
+                    image_block @ ImageBlock {
+                        content: ImageContent::Image(image),
+                        ..
+                    },
+                ) => Some((block_idx, image_block, image)),
+                _ => None,
+            })
+    }
 }
 
 #[derive(Clone)]
@@ -4,6 +4,9 @@ use std::fmt::Debug;
 pub type GlobalColorTable = ColorTable;
 pub type LocalColorTable = ColorTable;
 
+/// Most colors a color table can hold
+pub(crate) const MAX_COLORS: usize = 256;
+
 #[derive(Clone)]
 pub struct ColorTable {
     colors: Vec<Color>,
@@ -13,7 +16,7 @@ impl Debug for ColorTable {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("GlobalColorTable")
             .field("colors_length", &self.colors.len())
-            .field("first_color", &self.colors.get(0))
+            .field("first_color", &self.colors.first())
             .field("last_color", &self.colors.last())
             .finish()
     }
@@ -23,4 +26,36 @@ impl ColorTable {
     pub fn new(colors: Vec<Color>) -> Self {
         Self { colors }
     }
+
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    pub fn get(&self, idx: u8) -> Option<&Color> {
+        self.colors.get(idx as usize)
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// Value for the 3 bit size field of a packed byte, a table of this size
+    /// holds 2 ^ (n + 1) colors
+    pub fn size_field(&self) -> u8 {
+        let bits = usize::BITS - self.colors.len().saturating_sub(1).leading_zeros();
+
+        bits.saturating_sub(1).min(7) as u8
+    }
+
+    /// Pads the table with black up to the size given by [`ColorTable::size_field`]
+    pub fn padded(mut self) -> Self {
+        let len = 1 << (self.size_field() + 1);
+        self.colors.resize(len, Color::from_triple((0, 0, 0)));
+
+        self
+    }
 }
@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use super::Gif;
+
+/// How frame delays are turned into display times
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DelayPolicy {
+    /// Delays are used as stored in the file
+    #[default]
+    Exact,
+    /// Delays of 0 or 1 centiseconds are shown for 10 centiseconds, like
+    /// Chrome and Firefox do
+    Browser,
+}
+
+impl DelayPolicy {
+    const BROWSER_MIN_DELAY: u16 = 2;
+    const BROWSER_DEFAULT_DELAY: u16 = 10;
+
+    /// Effective delay in centiseconds
+    pub fn apply(&self, delay_time: u16) -> u16 {
+        match self {
+            DelayPolicy::Exact => delay_time,
+            DelayPolicy::Browser if delay_time < DelayPolicy::BROWSER_MIN_DELAY => {
+                DelayPolicy::BROWSER_DEFAULT_DELAY
+            }
+            DelayPolicy::Browser => delay_time,
+        }
+    }
+}
+
+/// Start times of every image frame of a [`Gif`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timeline {
+    start_times: Vec<Duration>,
+    duration: Duration,
+}
+
+impl Timeline {
+    /// Builds a timeline from frame delays in centiseconds
+    pub fn from_delays<I: IntoIterator<Item = u16>>(delays: I, policy: DelayPolicy) -> Self {
+        let mut start_times = Vec::new();
+        let mut duration = Duration::ZERO;
+
+        for delay_time in delays {
+            start_times.push(duration);
+            duration += centiseconds(policy.apply(delay_time));
+        }
+
+        Self {
+            start_times,
+            duration,
+        }
+    }
+
+    pub fn start_times(&self) -> &[Duration] {
+        &self.start_times
+    }
+
+    /// Time for one pass through the animation
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Index of the frame shown at `time`.
+    ///
+    /// The last frame stays on screen after the animation ends, so times past
+    /// [`Timeline::duration`] map to it. Returns `None` for an empty timeline.
+    pub fn frame_index_at(&self, time: Duration) -> Option<usize> {
+        // frames with zero delay share their start time with the next frame
+        // and are never actually on screen
+        let started = self.start_times.partition_point(|start| *start <= time);
+
+        started.checked_sub(1)
+    }
+}
+
+pub(crate) fn centiseconds(delay_time: u16) -> Duration {
+    Duration::from_millis(delay_time as u64 * 10)
+}
+
+/// Sums of consecutive delays, a new sum starts where the next delay would
+/// no longer fit into a frame
+pub(crate) fn merged_delays(delays: impl IntoIterator<Item = u16>) -> Vec<u16> {
+    let mut merged: Vec<u16> = Vec::new();
+
+    for delay_time in delays {
+        match merged.last_mut() {
+            Some(sum) if sum.checked_add(delay_time).is_some() => *sum += delay_time,
+            _ => merged.push(delay_time),
+        }
+    }
+
+    merged
+}
+
+impl Gif {
+    pub fn timeline(&self, policy: DelayPolicy) -> Timeline {
+        let delays = self
+            .images()
+            .map(|(_, block, _)| block.gce.as_ref().map_or(0, |gce| gce.delay_time()));
+
+        Timeline::from_delays(delays, policy)
+    }
+
+    /// Time for one pass through the animation with delays as stored
+    pub fn duration(&self) -> Duration {
+        self.timeline(DelayPolicy::Exact).duration()
+    }
+
+    pub fn frame_start_times(&self) -> Vec<Duration> {
+        self.timeline(DelayPolicy::Exact).start_times.clone()
+    }
+
+    /// Index of the image frame shown at `time`, see [`Timeline::frame_index_at`]
+    pub fn frame_index_at(&self, time: Duration) -> Option<usize> {
+        self.timeline(DelayPolicy::Exact).frame_index_at(time)
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::gif::{
+        Block, GifVersion, ImageContent, ImageData,
+        descriptor::{ImageDescriptor, LogicalScreenDescriptor},
+        extension::GraphicControlExtension,
+    };
+
+    fn gif_with_delays(delays: &[u16]) -> Gif {
+        let blocks = delays
+            .iter()
+            .map(|delay| {
+                let gce = GraphicControlExtension::from_tuple((4, 0, *delay, 0));
+                let content = ImageContent::image_from_tuple((
+                    ImageDescriptor::from_tuple((0, 0, 1, 1, 0)),
+                    None,
+                    ImageData::from_tuple((2, Vec::new())),
+                ));
+                Block::image_block_from_tuple((Some(gce), content))
+            })
+            .collect();
+
+        Gif::from_tuple((
+            GifVersion::V89a,
+            LogicalScreenDescriptor::from_tuple((1, 1, 0, 0, 0)),
+            None,
+            blocks,
+        ))
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn sum_delays_into_duration() {
+        let gif = gif_with_delays(&[10, 0, 25]);
+
+        assert_eq!(gif.duration(), ms(350));
+        assert_eq!(gif.frame_start_times(), vec![ms(0), ms(100), ms(100)]);
+    }
+
+    #[test]
+    fn clamp_short_delays_like_browsers() {
+        let timeline = gif_with_delays(&[0, 1, 2]).timeline(DelayPolicy::Browser);
+
+        assert_eq!(timeline.start_times(), &[ms(0), ms(100), ms(200)]);
+        assert_eq!(timeline.duration(), ms(220));
+    }
+
+    #[test]
+    fn find_frame_at_time() {
+        let timeline = Timeline::from_delays([10, 0, 25], DelayPolicy::Exact);
+
+        assert_eq!(timeline.frame_index_at(ms(0)), Some(0));
+        assert_eq!(timeline.frame_index_at(ms(99)), Some(0));
+        // zero delay frame is skipped
+        assert_eq!(timeline.frame_index_at(ms(100)), Some(2));
+        assert_eq!(timeline.frame_index_at(ms(10_000)), Some(2));
+        assert_eq!(
+            Timeline::from_delays([], DelayPolicy::Exact).frame_index_at(ms(0)),
+            None
+        );
+    }
+}
@@ -1,4 +1,8 @@
 /// Gif decoder
 pub mod decoder;
+/// Gif encoder
+pub mod encoder;
 /// Gif data structures
 pub mod gif;
+/// Edits that decode and encode the frames again
+pub mod transform;
@@ -0,0 +1,149 @@
+use std::ops::Range;
+
+use super::{TransformError, crop, similar};
+use crate::decoder::composite;
+use crate::gif::{Gif, Rect, Rgba};
+
+/// Crops away borders that keep one color, or stay transparent, in every
+/// frame.
+///
+/// Each side is compared against its first pixel in the first frame, colors
+/// count as the same when no channel differs by more than `tolerance`. GIFs
+/// that are uniform everywhere or have no frames are returned unchanged.
+pub fn autocrop(gif: &Gif, tolerance: u8) -> Result<Gif, TransformError> {
+    let frames: Vec<Vec<Rgba>> = composite(gif)?
+        .into_iter()
+        .map(|frame| frame.pixels)
+        .collect();
+    if frames.is_empty() {
+        return Ok(gif.clone());
+    }
+
+    let borders = Borders {
+        frames: &frames,
+        width: gif.lsd.canvas_width as usize,
+        tolerance,
+    };
+    let (width, height) = (gif.lsd.canvas_width, gif.lsd.canvas_height);
+
+    let Some(top) = (0..height).find(|y| !borders.uniform_row(*y, 0..width, (0, 0))) else {
+        return Ok(gif.clone());
+    };
+    let bottom = (top..height)
+        .rev()
+        .find(|y| !borders.uniform_row(*y, 0..width, (0, height - 1)))
+        .unwrap_or(top);
+    let rows = top..bottom + 1;
+    let left = (0..width)
+        .find(|x| !borders.uniform_column(*x, rows.clone(), (0, top)))
+        .unwrap_or(0);
+    let right = (left..width)
+        .rev()
+        .find(|x| !borders.uniform_column(*x, rows.clone(), (width - 1, top)))
+        .unwrap_or(left);
+
+    crop(
+        gif,
+        Rect::new(left, top, right - left + 1, bottom - top + 1),
+    )
+}
+
+struct Borders<'a> {
+    frames: &'a [Vec<Rgba>],
+    width: usize,
+    tolerance: u8,
+}
+
+impl Borders<'_> {
+    fn pixel(&self, frame: &[Rgba], (x, y): (u16, u16)) -> Rgba {
+        frame[y as usize * self.width + x as usize]
+    }
+
+    /// Whether every pixel of `row` in every frame looks like the pixel at
+    /// `reference` in the first frame
+    fn uniform_row(&self, row: u16, columns: Range<u16>, reference: (u16, u16)) -> bool {
+        self.uniform(columns.map(|x| (x, row)), reference)
+    }
+
+    fn uniform_column(&self, column: u16, rows: Range<u16>, reference: (u16, u16)) -> bool {
+        self.uniform(rows.map(|y| (column, y)), reference)
+    }
+
+    fn uniform(
+        &self,
+        positions: impl Iterator<Item = (u16, u16)> + Clone,
+        reference: (u16, u16),
+    ) -> bool {
+        let reference = self.pixel(&self.frames[0], reference);
+
+        self.frames.iter().all(|frame| {
+            positions
+                .clone()
+                .all(|position| similar(self.pixel(frame, position), reference, self.tolerance))
+        })
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::encoder::Encoder;
+    use crate::gif::{Color, builder::GifBuilder};
+    use crate::transform::rendered;
+
+    const BLACK: Rgba = [0, 0, 0, 255];
+    const NEAR_BLACK: Rgba = [6, 4, 0, 255];
+    const WHITE: Rgba = [255, 255, 255, 255];
+
+    /// Letterboxed 6x5 recording with content moving inside columns 2..4 and
+    /// rows 1..3
+    fn letterboxed() -> Gif {
+        let mut encoder = Encoder::new(6, 5);
+        for frame in 0..2 {
+            let mut pixels = [BLACK; 30];
+            pixels[29] = NEAR_BLACK;
+            pixels[6 + 2 + frame] = WHITE;
+            pixels[12 + 3] = WHITE;
+            encoder.add_frame(&pixels.concat(), 10).unwrap();
+        }
+
+        encoder.encode().unwrap()
+    }
+
+    #[test]
+    fn trim_borders_uniform_in_every_frame() {
+        let gif = letterboxed();
+
+        let trimmed = autocrop(&gif, 8).unwrap();
+
+        assert_eq!(
+            (trimmed.lsd.canvas_width, trimmed.lsd.canvas_height),
+            (2, 2)
+        );
+        assert!(rendered(&trimmed) == rendered(&crop(&gif, Rect::new(2, 1, 2, 2)).unwrap()));
+    }
+
+    #[test]
+    fn keep_borders_outside_the_tolerance() {
+        let gif = letterboxed();
+
+        let trimmed = autocrop(&gif, 2).unwrap();
+
+        assert_eq!(
+            (trimmed.lsd.canvas_width, trimmed.lsd.canvas_height),
+            (4, 4)
+        );
+    }
+
+    #[test]
+    fn keep_gifs_without_frames() {
+        let gif = GifBuilder::new(3, 3)
+            .global_palette(vec![Color::from_triple((0, 0, 0))])
+            .build()
+            .unwrap();
+
+        let trimmed = autocrop(&gif, 0).unwrap();
+
+        assert_eq!(trimmed.to_bytes().unwrap(), gif.to_bytes().unwrap());
+    }
+}
@@ -0,0 +1,112 @@
+use super::{Animation, TransformError};
+use crate::gif::Gif;
+
+/// Turns every frame of `gif` into a full canvas image that does not depend
+/// on the frames before it.
+///
+/// Every frame uses [`DisposalMethod::Keep`], except a frame followed by one
+/// that is transparent where it is opaque. No disposal that keeps the frame
+/// can show those pixels as transparent, so that frame uses
+/// [`DisposalMethod::RestoreBackground`]. Canvases with more than 256 colors
+/// are quantized.
+///
+/// [`DisposalMethod::Keep`]: crate::gif::extension::DisposalMethod::Keep
+/// [`DisposalMethod::RestoreBackground`]: crate::gif::extension::DisposalMethod::RestoreBackground
+pub fn coalesce(gif: &Gif) -> Result<Gif, TransformError> {
+    let animation = Animation::coalesce(gif)?;
+
+    let mut encoder = animation.encoder()?;
+    encoder.delta_frames(false);
+    let mut coalesced = encoder.encode()?;
+    animation.add_extensions(&mut coalesced);
+
+    Ok(coalesced)
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::decoder::{decode_indices, parse};
+    use crate::encoder::Encoder;
+    use crate::gif::{Rect, Rgba, extension::DisposalMethod};
+    use crate::transform::{moving_square, rendered};
+
+    #[test]
+    fn draw_every_frame_on_the_full_canvas() {
+        let gif = moving_square();
+        let canvas = Rect::new(0, 0, gif.lsd.canvas_width, gif.lsd.canvas_height);
+
+        let coalesced = coalesce(&gif).unwrap();
+
+        let coalesced = parse(coalesced.to_bytes().unwrap().as_slice()).unwrap();
+        assert_eq!(coalesced.loop_count(), gif.loop_count());
+        let frames = rendered(&coalesced);
+        assert!(frames == rendered(&gif));
+
+        // the second frame is cleared for the transparent pixel of the third
+        let mut disposals = Vec::new();
+        for ((_, block, image), (pixels, _)) in coalesced.images().zip(frames) {
+            assert_eq!(image.descriptor.rect(), canvas);
+            let gce = block.gce.as_ref().unwrap();
+            disposals.push(gce.disposal_method());
+
+            let table = image.lct.as_ref().or(coalesced.gct.as_ref()).unwrap();
+            let standalone: Vec<Rgba> = decode_indices(image)
+                .unwrap()
+                .iter()
+                .map(|idx| match gce.transparent_color_idx() == Some(*idx) {
+                    true => [0, 0, 0, 0],
+                    false => {
+                        let color = table.get(*idx).unwrap();
+                        [color.r, color.g, color.b, 255]
+                    }
+                })
+                .collect();
+            assert!(standalone == pixels);
+        }
+        assert_eq!(
+            disposals,
+            vec![
+                DisposalMethod::Keep,
+                DisposalMethod::RestoreBackground,
+                DisposalMethod::Keep,
+                DisposalMethod::Keep
+            ]
+        );
+    }
+
+    #[test]
+    fn clear_frames_before_transparent_ones() {
+        let red = [255, 0, 0, 255];
+        let clear = [0, 0, 0, 0];
+        let mut encoder = Encoder::new(2, 1);
+        encoder
+            .add_frame(&[red, red].concat(), 10)
+            .unwrap()
+            .add_frame(&[clear, red].concat(), 10)
+            .unwrap()
+            .add_frame(&[clear, clear].concat(), 10)
+            .unwrap()
+            .add_frame(&[clear, red].concat(), 10)
+            .unwrap();
+        let gif = encoder.encode().unwrap();
+
+        let coalesced = coalesce(&gif).unwrap();
+
+        assert!(rendered(&coalesced) == rendered(&gif));
+        assert_eq!(rendered(&coalesced)[2].0, vec![clear, clear]);
+        let disposals: Vec<DisposalMethod> = coalesced
+            .images()
+            .map(|(_, block, _)| block.gce.as_ref().unwrap().disposal_method())
+            .collect();
+        assert_eq!(
+            disposals,
+            vec![
+                DisposalMethod::RestoreBackground,
+                DisposalMethod::RestoreBackground,
+                DisposalMethod::Keep,
+                DisposalMethod::Keep
+            ]
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use super::TransformError;
+use crate::decoder::decode_indices;
+use crate::encoder::lzw_encode;
+use crate::gif::{
+    Block, Gif, Image, ImageBlock, ImageContent, Rect,
+    descriptor::ImageDescriptor,
+    extension::{DisposalMethod, GraphicControlExtension},
+};
+
+/// Cuts `rect` out of the canvas of `gif`.
+///
+/// Frames inside the rectangle are only moved and frames outside of it are
+/// dropped without decoding their image data, only frames on its edges are
+/// decoded and clipped. The delays of dropped frames stay in the animation.
+pub fn crop(gif: &Gif, rect: Rect) -> Result<Gif, TransformError> {
+    let canvas = Rect::new(0, 0, gif.lsd.canvas_width, gif.lsd.canvas_height);
+    if rect.is_empty() || rect.intersection(&canvas) != Some(rect) {
+        return Err(TransformError::InvalidData(
+            format!("{rect:?} is not inside the {canvas:?} canvas").into(),
+        ));
+    }
+
+    let mut blocks = Vec::with_capacity(gif.blocks.len());
+    // block of the last kept image, dropped frames can add their delay to it
+    let mut last_image: Option<usize> = None;
+
+    for block in &gif.blocks {
+        let Block::Image(ImageBlock {
+            gce,
+            content: ImageContent::Image(image),
+        }) = block
+        else {
+            blocks.push(block.clone());
+            continue;
+        };
+
+        let cropped = match image.descriptor.rect().intersection(&rect) {
+            Some(visible) if visible == image.descriptor.rect() => moved(image, &rect),
+            Some(visible) => clipped(image, &visible, &rect)?,
+            None => {
+                let delay_time = gce.as_ref().map_or(0, |gce| gce.delay_time());
+                if delay_time == 0 || extend_delay(&mut blocks, last_image, delay_time) {
+                    continue;
+                }
+                // nothing before this frame keeps showing, so its time on
+                // screen is taken by an invisible frame
+                last_image = Some(blocks.len());
+                blocks.push(invisible(image, delay_time));
+                continue;
+            }
+        };
+
+        last_image = Some(blocks.len());
+        blocks.push(Block::Image(ImageBlock {
+            gce: gce.clone(),
+            content: ImageContent::Image(cropped),
+        }));
+    }
+
+    let mut lsd = gif.lsd.clone();
+    lsd.canvas_width = rect.width;
+    lsd.canvas_height = rect.height;
+
+    Ok(Gif::from_tuple((
+        gif.version.clone(),
+        lsd,
+        gif.gct.clone(),
+        blocks,
+    )))
+}
+
+/// Adds `delay_time` to the last kept image if it stays on screen after its
+/// delay, which is what happened while the dropped frame was shown
+fn extend_delay(blocks: &mut [Block], last_image: Option<usize>, delay_time: u16) -> bool {
+    let Some(Block::Image(ImageBlock { gce, .. })) = last_image.map(|idx| &mut blocks[idx]) else {
+        return false;
+    };
+    let gce = gce
+        .get_or_insert_with(|| GraphicControlExtension::new(0, DisposalMethod::Unspecified, None));
+    let extended = gce.delay_time().checked_add(delay_time);
+    match (gce.disposal_method(), extended) {
+        (DisposalMethod::Unspecified | DisposalMethod::Keep, Some(extended)) => {
+            gce.set_delay_time(extended);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Image inside the crop, only its offset changes
+fn moved(image: &Image, crop: &Rect) -> Image {
+    let mut moved = image.clone();
+    moved.descriptor.left -= crop.left;
+    moved.descriptor.top -= crop.top;
+    moved
+}
+
+/// Image on the edge of the crop, reduced to its `visible` part
+fn clipped(image: &Image, visible: &Rect, crop: &Rect) -> Result<Image, TransformError> {
+    let rect = image.descriptor.rect();
+    let indices = decode_indices(image)?;
+
+    let mut clipped = Vec::with_capacity(visible.width as usize * visible.height as usize);
+    for y in visible.top..visible.top + visible.height {
+        let row = (y - rect.top) as usize * rect.width as usize;
+        let start = row + (visible.left - rect.left) as usize;
+        clipped.extend_from_slice(&indices[start..start + visible.width as usize]);
+    }
+
+    let mut descriptor = ImageDescriptor::new(
+        visible.left - crop.left,
+        visible.top - crop.top,
+        visible.width,
+        visible.height,
+    );
+    descriptor.set_local_color_table(image.lct.as_ref());
+
+    Ok(Image {
+        descriptor,
+        lct: image.lct.clone(),
+        data: lzw_encode(&clipped, image.data.lzw_min_code_size),
+    })
+}
+
+/// Transparent single pixel frame shown for `delay_time`
+fn invisible(image: &Image, delay_time: u16) -> Block {
+    let mut descriptor = ImageDescriptor::new(0, 0, 1, 1);
+    descriptor.set_local_color_table(image.lct.as_ref());
+
+    Block::Image(ImageBlock {
+        gce: Some(GraphicControlExtension::new(
+            delay_time,
+            DisposalMethod::Keep,
+            Some(0),
+        )),
+        content: ImageContent::Image(Image {
+            descriptor,
+            lct: image.lct.clone(),
+            data: lzw_encode(&[0], image.data.lzw_min_code_size),
+        }),
+    })
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::decoder::parse;
+    use crate::gif::{
+        Color, Rgba,
+        builder::{FrameBuilder, GifBuilder},
+    };
+    use crate::transform::{moving_square, one_bit_gif, rendered};
+
+    /// Pixels of `rect` in every rendered frame
+    fn cut(frames: Vec<(Vec<Rgba>, u16)>, width: u16, rect: Rect) -> Vec<(Vec<Rgba>, u16)> {
+        frames
+            .into_iter()
+            .map(|(pixels, delay_time)| {
+                let pixels = (rect.top..rect.top + rect.height)
+                    .flat_map(|y| {
+                        let start = y as usize * width as usize + rect.left as usize;
+                        pixels[start..start + rect.width as usize].to_vec()
+                    })
+                    .collect();
+                (pixels, delay_time)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn crop_every_frame_of_an_animation() {
+        let gif = moving_square();
+        let rect = Rect::new(1, 1, 5, 3);
+
+        let cropped = crop(&gif, rect).unwrap();
+
+        let cropped = parse(cropped.to_bytes().unwrap().as_slice()).unwrap();
+        assert_eq!(
+            (cropped.lsd.canvas_width, cropped.lsd.canvas_height),
+            (5, 3)
+        );
+        assert!(rendered(&cropped) == cut(rendered(&gif), gif.lsd.canvas_width, rect));
+    }
+
+    #[test]
+    fn keep_the_delays_of_dropped_frames() {
+        let palette = vec![
+            Color::from_triple((255, 0, 0)),
+            Color::from_triple((0, 0, 255)),
+        ];
+        let mut builder = GifBuilder::new(4, 1);
+        builder.global_palette(palette).frame(
+            FrameBuilder::new(2, 1, vec![0, 0])
+                .delay_time(10)
+                .disposal(DisposalMethod::Keep),
+        );
+        for (left, delay_time, disposal) in [
+            (3, 20, DisposalMethod::Keep),
+            (1, 30, DisposalMethod::RestoreBackground),
+            (3, 40, DisposalMethod::Keep),
+            (0, 50, DisposalMethod::Keep),
+        ] {
+            builder.frame(
+                FrameBuilder::new(1, 1, vec![1])
+                    .position(left, 0)
+                    .delay_time(delay_time)
+                    .disposal(disposal),
+            );
+        }
+        let gif = builder.build().unwrap();
+        let rect = Rect::new(0, 0, 2, 1);
+
+        let cropped = crop(&gif, rect).unwrap();
+
+        assert_eq!(cropped.images().count(), 4);
+        let mut expected = cut(rendered(&gif), 4, rect);
+        expected[0].1 += expected.remove(1).1;
+        assert!(rendered(&cropped) == expected);
+        assert!(crop(&gif, Rect::new(3, 0, 2, 1)).is_err());
+    }
+
+    #[test]
+    fn clip_frames_with_one_bit_codes() {
+        let gif = one_bit_gif();
+
+        let cropped = crop(&gif, Rect::new(0, 0, 1, 1)).unwrap();
+
+        assert!(rendered(&cropped) == vec![(vec![[255, 0, 0, 255]], 0)]);
+    }
+}
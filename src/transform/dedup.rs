@@ -0,0 +1,81 @@
+use super::{Animation, TransformError, similar};
+use crate::decoder::CompositedFrame;
+use crate::gif::{Gif, Rgba};
+
+/// Merges runs of consecutive frames that render the same into their first
+/// frame, which is shown for the delays of the whole run.
+///
+/// Frames are compared after compositing, pixels count as the same when no
+/// channel differs by more than `tolerance` from the first frame of the run.
+/// A run is split where its delay would no longer fit into a frame.
+pub fn dedup(gif: &Gif, tolerance: u8) -> Result<Gif, TransformError> {
+    let mut animation = Animation::coalesce(gif)?;
+
+    let mut frames: Vec<CompositedFrame> = Vec::with_capacity(animation.frames.len());
+    for frame in animation.frames {
+        let merged = frames.last_mut().and_then(|kept| {
+            let delay_time = kept.delay_time.checked_add(frame.delay_time)?;
+            duplicate(&kept.pixels, &frame.pixels, tolerance).then_some((kept, delay_time))
+        });
+
+        match merged {
+            Some((kept, delay_time)) => kept.delay_time = delay_time,
+            None => frames.push(frame),
+        }
+    }
+    animation.frames = frames;
+
+    animation.encode()
+}
+
+fn duplicate(kept: &[Rgba], frame: &[Rgba], tolerance: u8) -> bool {
+    kept.iter()
+        .zip(frame)
+        .all(|(kept, pixel)| similar(*kept, *pixel, tolerance))
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::encoder::Encoder;
+    use crate::transform::rendered;
+
+    #[test]
+    fn merge_frames_within_the_tolerance() {
+        let shade = |value: u8| -> Rgba { [value, 0, 0, 255] };
+        let mut encoder = Encoder::new(4, 4);
+        encoder.delta_frames(false);
+        for (value, delay_time) in [(100, 10), (102, 20), (104, 30), (106, 5), (200, 7)] {
+            encoder
+                .add_frame(&[shade(value); 16].concat(), delay_time)
+                .unwrap();
+        }
+        let gif = encoder.encode().unwrap();
+
+        let deduped = dedup(&gif, 4).unwrap();
+
+        let frames = rendered(&deduped);
+        assert_eq!(
+            frames
+                .iter()
+                .map(|(pixels, delay_time)| (pixels[0], *delay_time))
+                .collect::<Vec<_>>(),
+            vec![(shade(100), 60), (shade(106), 5), (shade(200), 7)]
+        );
+        assert_eq!(rendered(&dedup(&gif, 0).unwrap()).len(), 5);
+    }
+
+    #[test]
+    fn split_runs_longer_than_a_frame_can_show() {
+        let mut encoder = Encoder::new(1, 1);
+        for delay_time in [60_000, 60_000, 1] {
+            encoder.add_frame(&[9, 9, 9, 255], delay_time).unwrap();
+        }
+        let gif = encoder.encode().unwrap();
+
+        let deduped = dedup(&gif, 0).unwrap();
+
+        let delays: Vec<u16> = rendered(&deduped).iter().map(|frame| frame.1).collect();
+        assert_eq!(delays, vec![60_000, 60_001]);
+    }
+}
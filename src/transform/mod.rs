@@ -0,0 +1,235 @@
+use std::borrow::Cow;
+
+use thiserror::Error;
+
+use crate::decoder::{CompositedFrame, Compositor, DecodeError, TRANSPARENT};
+use crate::encoder::{EncodeError, Encoder, optimize};
+use crate::gif::{Block, Gif, ImageBlock, ImageContent, Rgba, extension::ApplicationExtension};
+
+mod autocrop;
+mod coalesce;
+mod crop;
+mod dedup;
+mod pad;
+mod resize;
+mod retime;
+mod rotate;
+mod scale;
+mod sequence;
+mod trim;
+
+pub use autocrop::autocrop;
+pub use coalesce::coalesce;
+pub use crop::crop;
+pub use dedup::dedup;
+pub use pad::{Fill, pad};
+pub use resize::{Filter, resize, resize_to_fit};
+pub use retime::{resample_fps, scale_delays, set_fps};
+pub use rotate::{Flip, Rotation, flip, rotate};
+pub use scale::scale_indices;
+pub use sequence::{concat, every_nth, ping_pong, remove_range, reverse};
+pub use trim::trim;
+
+#[derive(Debug, Error)]
+pub enum TransformError {
+    #[error("Invalid transform: {0}")]
+    InvalidData(Cow<'static, str>),
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+    #[error(transparent)]
+    Encode(#[from] EncodeError),
+}
+
+/// Full canvas frames of a GIF, so frames can be moved around without
+/// depending on the ones drawn before them
+#[derive(Debug, Clone)]
+pub(crate) struct Animation {
+    width: u16,
+    height: u16,
+    loop_count: Option<u16>,
+    /// Comments, application extensions and plain text of the source GIF,
+    /// keyed by their block index
+    extensions: Vec<(usize, Block)>,
+    frames: Vec<CompositedFrame>,
+}
+
+impl Animation {
+    /// Draws every frame of `gif` onto its own copy of the canvas
+    pub(crate) fn coalesce(gif: &Gif) -> Result<Self, DecodeError> {
+        Ok(Self {
+            width: gif.lsd.canvas_width,
+            height: gif.lsd.canvas_height,
+            loop_count: gif.loop_count(),
+            // the encoder writes the loop count again
+            extensions: gif
+                .blocks
+                .iter()
+                .enumerate()
+                .filter(|(_, block)| {
+                    !matches!(
+                        block,
+                        Block::ApplicationExtension(ApplicationExtension::NetScape(_))
+                            | Block::Image(ImageBlock {
+                                content: ImageContent::Image(_),
+                                ..
+                            })
+                    )
+                })
+                .map(|(block_idx, block)| (block_idx, block.clone()))
+                .collect(),
+            frames: Compositor::new(gif).collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Centers the frames on a larger canvas, the new area is transparent
+    pub(crate) fn with_canvas(mut self, width: u16, height: u16) -> Self {
+        if (width, height) == (self.width, self.height) {
+            return self;
+        }
+
+        self.drop_plain_text();
+        let left = (width - self.width) as usize / 2;
+        let top = (height - self.height) as usize / 2;
+        for frame in &mut self.frames {
+            let mut pixels = vec![TRANSPARENT; width as usize * height as usize];
+            for (y, row) in frame.pixels.chunks(self.width as usize).enumerate() {
+                let start = (top + y) * width as usize + left;
+                pixels[start..start + row.len()].copy_from_slice(row);
+            }
+            frame.pixels = pixels;
+        }
+
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Encoder holding the frames, loop count included
+    pub(crate) fn encoder(&self) -> Result<Encoder, TransformError> {
+        if self.frames.is_empty() {
+            return Err(TransformError::InvalidData("no frames left".into()));
+        }
+
+        let mut encoder = Encoder::new(self.width, self.height);
+        if let Some(loop_count) = self.loop_count {
+            encoder.loop_count(loop_count);
+        }
+        for frame in &self.frames {
+            encoder.add_frame(&frame.pixels.concat(), frame.delay_time)?;
+        }
+
+        Ok(encoder)
+    }
+
+    /// Plain text is placed on the canvas in pixels, so it is dropped when
+    /// the canvas changes
+    pub(crate) fn drop_plain_text(&mut self) {
+        self.extensions
+            .retain(|(_, block)| !matches!(block, Block::Image(_)));
+    }
+
+    /// Adds the extensions of the source GIF to `gif`, which holds one image
+    /// per frame. Each goes in front of the first frame drawn from a later
+    /// block, the ones after every frame go last.
+    pub(crate) fn add_extensions(&self, gif: &mut Gif) {
+        let mut extensions = self.extensions.iter().peekable();
+        let mut frames = self.frames.iter();
+
+        for block in std::mem::take(&mut gif.blocks) {
+            if let Block::Image(ImageBlock {
+                content: ImageContent::Image(_),
+                ..
+            }) = block
+                && let Some(frame) = frames.next()
+            {
+                while let Some((_, extension)) =
+                    extensions.next_if(|(block_idx, _)| *block_idx < frame.block_idx)
+                {
+                    gif.blocks.push(extension.clone());
+                }
+            }
+            gif.blocks.push(block);
+        }
+        gif.blocks
+            .extend(extensions.map(|(_, extension)| extension.clone()));
+    }
+
+    /// Encodes the frames as deltas again and optimizes the result
+    pub(crate) fn encode(&self) -> Result<Gif, TransformError> {
+        let mut gif = self.encoder()?.encode()?;
+        self.add_extensions(&mut gif);
+
+        Ok(optimize(&gif))
+    }
+}
+
+/// Whether two pixels are both transparent, or both opaque with no channel
+/// differing by more than `tolerance`
+pub(crate) fn similar(pixel: Rgba, other: Rgba, tolerance: u8) -> bool {
+    match (pixel[3], other[3]) {
+        (0, 0) => true,
+        (0, _) | (_, 0) => false,
+        _ => pixel[..3]
+            .iter()
+            .zip(&other[..3])
+            .all(|(channel, other)| channel.abs_diff(*other) <= tolerance),
+    }
+}
+
+/// Pixels of every frame, for comparing transforms in tests
+#[cfg(test)]
+pub(crate) fn rendered(gif: &Gif) -> Vec<(Vec<Rgba>, u16)> {
+    crate::decoder::composite(gif)
+        .unwrap()
+        .into_iter()
+        .map(|frame| (frame.pixels, frame.delay_time))
+        .collect()
+}
+
+/// 7x5 animation of a square moving over a gradient, encoded as delta
+/// frames with a global table and a transparent corner in the third frame
+#[cfg(test)]
+pub(crate) fn moving_square() -> Gif {
+    let mut encoder = Encoder::new(7, 5);
+    encoder.loop_count(0).global_palette(true);
+    for frame in 0..4 {
+        let pixels: Vec<Rgba> = (0..7 * 5)
+            .map(|idx| match (idx % 7, idx / 7) {
+                (6, 4) if frame == 2 => TRANSPARENT,
+                (x, y) if (frame..frame + 2).contains(&x) && (1..3).contains(&y) => {
+                    [255, 0, 0, 255]
+                }
+                (x, y) => [x as u8 * 30, y as u8 * 50, 100, 255],
+            })
+            .collect();
+        encoder
+            .add_frame(&pixels.concat(), 10 + frame as u16)
+            .unwrap();
+    }
+
+    encoder.encode().unwrap()
+}
+
+/// 2x1 GIF of a red and a blue pixel compressed with 1 bit codes, which
+/// decoders accept but the encoder does not write
+#[cfg(test)]
+pub(crate) fn one_bit_gif() -> Gif {
+    use crate::gif::{Block, Color, ImageContent, SubBlock, data::ImageData};
+
+    let mut gif = crate::gif::builder::GifBuilder::new(2, 1)
+        .global_palette(vec![
+            Color::from_triple((255, 0, 0)),
+            Color::from_triple((0, 0, 255)),
+        ])
+        .frame(&crate::gif::builder::FrameBuilder::new(2, 1, vec![0, 1]))
+        .build()
+        .unwrap();
+    if let Some(Block::Image(image)) = gif.blocks.last_mut()
+        && let ImageContent::Image(image) = &mut image.content
+    {
+        // clear, 0, 1 and end of information
+        image.data = ImageData::from_tuple((1, vec![SubBlock(vec![0x92, 0x01])]));
+    }
+
+    gif
+}
@@ -0,0 +1,218 @@
+use super::TransformError;
+use crate::decoder::decode_indices;
+use crate::encoder::IndexedFrame;
+use crate::gif::{Block, Color, Gif, ImageBlock, ImageContent, Rect, extension::DisposalMethod};
+
+/// What the margin around a padded GIF shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fill {
+    #[default]
+    Transparent,
+    /// Background color of the global color table
+    Background,
+}
+
+/// Centers `gif` on a `width * height` canvas.
+///
+/// Frames are only moved, their image data stays as it is. A transparent
+/// margin needs nothing else since the canvas starts out transparent, a
+/// background margin is drawn into the first frame, which then covers the
+/// whole canvas. That fails if the first frame clears itself or needs more
+/// than 256 colors with the background.
+pub fn pad(gif: &Gif, width: u16, height: u16, fill: Fill) -> Result<Gif, TransformError> {
+    let (canvas_width, canvas_height) = (gif.lsd.canvas_width, gif.lsd.canvas_height);
+    if width < canvas_width || height < canvas_height {
+        return Err(TransformError::InvalidData(
+            format!("can not pad {canvas_width}x{canvas_height} to {width}x{height}").into(),
+        ));
+    }
+
+    let left = (width - canvas_width) / 2;
+    let top = (height - canvas_height) / 2;
+
+    let mut padded = gif.clone();
+    padded.lsd.canvas_width = width;
+    padded.lsd.canvas_height = height;
+
+    for block in &mut padded.blocks {
+        let Block::Image(image_block) = block else {
+            continue;
+        };
+
+        match &mut image_block.content {
+            ImageContent::Image(image) => {
+                image.descriptor.left = image.descriptor.left.saturating_add(left);
+                image.descriptor.top = image.descriptor.top.saturating_add(top);
+            }
+            ImageContent::PlainText(text) if text.header.len() >= 4 => {
+                for (idx, offset) in [left, top].into_iter().enumerate() {
+                    let field = &mut text.header[idx * 2..idx * 2 + 2];
+                    let moved = u16::from_le_bytes([field[0], field[1]]).saturating_add(offset);
+                    field.copy_from_slice(&moved.to_le_bytes());
+                }
+            }
+            ImageContent::PlainText(_) => {}
+        }
+    }
+
+    let first_image = padded.images().next().map(|(block_idx, _, _)| block_idx);
+    if fill == Fill::Background
+        && let Some(block_idx) = first_image
+    {
+        let inner = Rect::new(left, top, canvas_width, canvas_height);
+        padded.blocks[block_idx] = with_margin(&padded, block_idx, inner)?;
+    }
+
+    Ok(padded)
+}
+
+/// First frame drawn over the whole canvas, the background color outside
+/// of `inner` and transparent where the frame does not reach inside of it
+fn with_margin(gif: &Gif, block_idx: usize, inner: Rect) -> Result<Block, TransformError> {
+    let Block::Image(ImageBlock {
+        gce,
+        content: ImageContent::Image(image),
+    }) = &gif.blocks[block_idx]
+    else {
+        unreachable!("images are image blocks");
+    };
+
+    let background = gif
+        .gct
+        .as_ref()
+        .and_then(|gct| gct.get(gif.lsd.background_color_idx))
+        .copied()
+        .ok_or(TransformError::InvalidData(
+            "the background color is not in a global color table".into(),
+        ))?;
+    let disposal = gce
+        .as_ref()
+        .map_or(DisposalMethod::Unspecified, |gce| gce.disposal_method());
+    if !matches!(disposal, DisposalMethod::Unspecified | DisposalMethod::Keep) {
+        return Err(TransformError::InvalidData(
+            "the first frame clears itself, so it can not keep the margin".into(),
+        ));
+    }
+
+    let table = image
+        .lct
+        .as_ref()
+        .or(gif.gct.as_ref())
+        .ok_or(TransformError::InvalidData(
+            "image has no color table defined".into(),
+        ))?;
+    let transparent_idx = gce.as_ref().and_then(|gce| gce.transparent_color_idx());
+    let rect = image.descriptor.rect();
+    let indices = decode_indices(image)?;
+
+    let (width, height) = (gif.lsd.canvas_width, gif.lsd.canvas_height);
+    let pixels: Vec<Option<Color>> = (0..height as u32)
+        .flat_map(|y| (0..width as u32).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            if !rect.contains(x, y) {
+                return (!inner.contains(x, y)).then_some(background);
+            }
+
+            let row = (y - rect.top as u32) as usize * rect.width as usize;
+            let idx = indices[row + (x - rect.left as u32) as usize];
+            // indices past the table are drawn in black
+            (Some(idx) != transparent_idx).then(|| table.get(idx).copied().unwrap_or_default())
+        })
+        .collect();
+
+    let delay_time = gce.as_ref().map_or(0, |gce| gce.delay_time());
+    let user_input = gce.as_ref().is_some_and(|gce| gce.user_input());
+    let frame = IndexedFrame::from_pixels(
+        Rect::new(0, 0, width, height),
+        delay_time,
+        disposal,
+        &pixels,
+    )
+    .ok_or(TransformError::InvalidData(
+        "the first frame and the background need more than 256 colors".into(),
+    ))?;
+
+    Ok(frame.local_block(user_input))
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::decoder::parse;
+    use crate::gif::Rgba;
+    use crate::transform::{moving_square, rendered};
+
+    /// Whether every frame of `padded` shows the frame of `gif` centered on
+    /// a margin of `margin`
+    fn centered(padded: &Gif, gif: &Gif, margin: Rgba) -> bool {
+        let (width, height) = (
+            gif.lsd.canvas_width as usize,
+            gif.lsd.canvas_height as usize,
+        );
+        let side = padded.lsd.canvas_width as usize;
+        let (left, top) = ((side - width) / 2, (side - height) / 2);
+
+        rendered(padded).iter().zip(rendered(gif)).all(
+            |((pixels, delay_time), (original, original_delay_time))| {
+                let inner = |idx: usize| {
+                    let (x, y) = (idx % side, idx / side);
+                    (left..left + width).contains(&x) && (top..top + height).contains(&y)
+                };
+
+                *delay_time == original_delay_time
+                    && pixels
+                        .iter()
+                        .enumerate()
+                        .all(|(idx, pixel)| match inner(idx) {
+                            true => {
+                                *pixel == original[(idx / side - top) * width + idx % side - left]
+                            }
+                            false => *pixel == margin,
+                        })
+            },
+        )
+    }
+
+    #[test]
+    fn center_frames_without_encoding_them_again() {
+        let gif = moving_square();
+        let side = gif.lsd.canvas_width.max(gif.lsd.canvas_height) + 1;
+
+        let padded = pad(&gif, side, side, Fill::Transparent).unwrap();
+
+        let padded = parse(padded.to_bytes().unwrap().as_slice()).unwrap();
+        assert_eq!(padded.images().count(), gif.images().count());
+        for ((_, _, padded), (_, _, original)) in padded.images().zip(gif.images()) {
+            assert_eq!(padded.data.sub_blocks, original.data.sub_blocks);
+        }
+        assert!(centered(&padded, &gif, [0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn fill_the_margin_with_the_background() {
+        let mut gif = moving_square();
+        gif.lsd.background_color_idx = 3;
+        let background = *gif.gct.as_ref().unwrap().get(3).unwrap();
+
+        let padded = pad(&gif, 9, 9, Fill::Background).unwrap();
+
+        assert_eq!(padded.images().count(), gif.images().count());
+        for ((_, _, padded), (_, _, original)) in padded.images().zip(gif.images()).skip(1) {
+            assert_eq!(padded.data.sub_blocks, original.data.sub_blocks);
+        }
+        let margin = [background.r, background.g, background.b, 255];
+        assert!(centered(&padded, &gif, margin));
+        assert!(pad(&gif, 6, 9, Fill::Background).is_err());
+    }
+
+    #[test]
+    fn refuse_a_margin_the_first_frame_clears() {
+        let mut gif = moving_square();
+        gif.graphic_control_mut(0)
+            .unwrap()
+            .set_disposal_method(DisposalMethod::RestoreBackground);
+
+        assert!(pad(&gif, 9, 9, Fill::Background).is_err());
+        assert!(pad(&gif, 9, 9, Fill::Transparent).is_ok());
+    }
+}
@@ -0,0 +1,243 @@
+use std::f32::consts::PI;
+
+use super::{Animation, TransformError};
+use crate::gif::{Gif, Rgba};
+
+/// How pixels are sampled when the canvas size changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    /// Closest source pixel, keeps hard edges and the original colors
+    Nearest,
+    /// Linear interpolation between the two closest pixels on each axis
+    Bilinear,
+    /// Windowed sinc over three pixels on each side, the sharpest filter
+    #[default]
+    Lanczos3,
+    /// Average of the source pixels each target pixel covers
+    Box,
+}
+
+impl Filter {
+    /// Distance in source pixels the filter reaches when upscaling
+    fn support(self) -> f32 {
+        match self {
+            Filter::Nearest => 0.0,
+            Filter::Box => 0.5,
+            Filter::Bilinear => 1.0,
+            Filter::Lanczos3 => 3.0,
+        }
+    }
+
+    fn weight(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            Filter::Nearest => 1.0,
+            Filter::Box => (x <= 0.5) as u8 as f32,
+            Filter::Bilinear => (1.0 - x).max(0.0),
+            Filter::Lanczos3 if x < 3.0 => sinc(x) * sinc(x / 3.0),
+            Filter::Lanczos3 => 0.0,
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Scales every frame of `gif` to `width * height` pixels.
+///
+/// Frames are coalesced and resampled in premultiplied RGBA, so transparent
+/// pixels do not bleed into their neighbors, then quantized and optimized
+/// again. Plain text is dropped when the size changes.
+pub fn resize(gif: &Gif, width: u16, height: u16, filter: Filter) -> Result<Gif, TransformError> {
+    if width == 0 || height == 0 {
+        return Err(TransformError::InvalidData(
+            format!("can not resize to {width}x{height}").into(),
+        ));
+    }
+
+    let mut animation = Animation::coalesce(gif)?;
+    let from = (animation.width, animation.height);
+    for frame in &mut animation.frames {
+        frame.pixels = resample(&frame.pixels, from, (width, height), filter);
+    }
+    if (width, height) != from {
+        animation.drop_plain_text();
+    }
+    animation.width = width;
+    animation.height = height;
+
+    animation.encode()
+}
+
+/// Scales `gif` down to fit within `max_width * max_height` while keeping
+/// its aspect ratio. GIFs that already fit are only optimized, never
+/// enlarged.
+pub fn resize_to_fit(
+    gif: &Gif,
+    max_width: u16,
+    max_height: u16,
+    filter: Filter,
+) -> Result<Gif, TransformError> {
+    let (width, height) = (gif.lsd.canvas_width, gif.lsd.canvas_height);
+    let scale = (max_width as f64 / width as f64)
+        .min(max_height as f64 / height as f64)
+        .min(1.0);
+    let scaled = |len: u16| ((len as f64 * scale).round() as u16).max(1);
+
+    resize(gif, scaled(width), scaled(height), filter)
+}
+
+/// Source pixels and their weights for one target pixel
+struct Contribution {
+    start: usize,
+    weights: Vec<f32>,
+}
+
+fn contributions(from: usize, to: usize, filter: Filter) -> Vec<Contribution> {
+    let scale = from as f32 / to as f32;
+    // downscaling stretches the filter over every source pixel it covers
+    let filter_scale = scale.max(1.0);
+    let support = filter.support() * filter_scale;
+
+    (0..to)
+        .map(|idx| {
+            let center = (idx as f32 + 0.5) * scale;
+            let nearest = Contribution {
+                start: (center as usize).min(from - 1),
+                weights: vec![1.0],
+            };
+            if filter == Filter::Nearest {
+                return nearest;
+            }
+
+            let start = (center - support).floor().max(0.0) as usize;
+            let end = ((center + support).ceil() as usize).min(from);
+            let weights: Vec<f32> = (start..end)
+                .map(|src| filter.weight((src as f32 + 0.5 - center) / filter_scale))
+                .collect();
+
+            let sum: f32 = weights.iter().sum();
+            if sum.abs() < f32::EPSILON {
+                return nearest;
+            }
+
+            Contribution {
+                start,
+                weights: weights.into_iter().map(|weight| weight / sum).collect(),
+            }
+        })
+        .collect()
+}
+
+/// Resamples rows and then columns of a canvas
+fn resample(pixels: &[Rgba], from: (u16, u16), to: (u16, u16), filter: Filter) -> Vec<Rgba> {
+    if from == to {
+        return pixels.to_vec();
+    }
+
+    let (width, height) = (from.0 as usize, from.1 as usize);
+    let (to_width, to_height) = (to.0 as usize, to.1 as usize);
+
+    let premultiplied: Vec<[f32; 4]> = pixels
+        .iter()
+        .map(|[r, g, b, a]| {
+            let alpha = *a as f32 / 255.0;
+            [
+                *r as f32 * alpha,
+                *g as f32 * alpha,
+                *b as f32 * alpha,
+                *a as f32,
+            ]
+        })
+        .collect();
+
+    let columns = contributions(width, to_width, filter);
+    let mut rows_scaled = Vec::with_capacity(to_width * height);
+    for row in premultiplied.chunks(width) {
+        rows_scaled.extend(columns.iter().map(|c| convolve(c, |src| row[src])));
+    }
+
+    let rows = contributions(height, to_height, filter);
+    let mut scaled = Vec::with_capacity(to_width * to_height);
+    for contribution in &rows {
+        for x in 0..to_width {
+            let [r, g, b, a] = convolve(contribution, |src| rows_scaled[src * to_width + x]);
+            let alpha = a.clamp(0.0, 255.0);
+            let color = |channel: f32| match alpha {
+                0.0 => 0,
+                _ => (channel * 255.0 / alpha).round().clamp(0.0, 255.0) as u8,
+            };
+            scaled.push([color(r), color(g), color(b), alpha.round() as u8]);
+        }
+    }
+
+    scaled
+}
+
+fn convolve(contribution: &Contribution, pixel: impl Fn(usize) -> [f32; 4]) -> [f32; 4] {
+    let mut sum = [0.0; 4];
+
+    for (offset, weight) in contribution.weights.iter().enumerate() {
+        let pixel = pixel(contribution.start + offset);
+        for (sum, channel) in sum.iter_mut().zip(pixel) {
+            *sum += channel * weight;
+        }
+    }
+
+    sum
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::transform::{moving_square, rendered};
+
+    #[test]
+    fn make_thumbnails_that_keep_the_animation() {
+        let gif = moving_square();
+
+        let thumbnail = resize_to_fit(&gif, 14, 3, Filter::Lanczos3).unwrap();
+
+        assert_eq!(
+            (thumbnail.lsd.canvas_width, thumbnail.lsd.canvas_height),
+            (4, 3)
+        );
+        let delays = |gif: &Gif| -> Vec<u16> { rendered(gif).iter().map(|f| f.1).collect() };
+        assert_eq!(delays(&thumbnail), delays(&gif));
+        assert_eq!(thumbnail.loop_count(), gif.loop_count());
+        assert!(thumbnail.encoded_len().unwrap() < gif.encoded_len().unwrap());
+    }
+
+    #[test]
+    fn sample_with_each_filter() {
+        let black = [0, 0, 0, 255];
+        let white = [255, 255, 255, 255];
+        let pixels = [black, white, black, white];
+
+        let nearest = resample(&pixels, (4, 1), (2, 1), Filter::Nearest);
+        assert_eq!(nearest, vec![white, white]);
+        let boxed = resample(&pixels, (4, 1), (2, 1), Filter::Box);
+        assert_eq!(boxed, vec![[128, 128, 128, 255]; 2]);
+        let bilinear = resample(&[black, white], (2, 1), (4, 1), Filter::Bilinear);
+        assert_eq!(
+            bilinear.iter().map(|pixel| pixel[0]).collect::<Vec<_>>(),
+            vec![0, 64, 191, 255]
+        );
+        let lanczos = resample(&[white; 9], (3, 3), (2, 2), Filter::Lanczos3);
+        assert_eq!(lanczos, vec![white; 4]);
+    }
+
+    #[test]
+    fn keep_transparent_pixels_from_darkening_colors() {
+        let red = [255, 0, 0, 255];
+
+        let scaled = resample(&[red, [0, 0, 0, 0]], (2, 1), (1, 1), Filter::Box);
+
+        assert_eq!(scaled, vec![[255, 0, 0, 128]]);
+    }
+}
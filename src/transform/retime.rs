@@ -0,0 +1,248 @@
+use std::cmp::Reverse;
+use std::time::Duration;
+
+use super::{Animation, TransformError};
+use crate::gif::{
+    Gif,
+    timeline::{DelayPolicy, Timeline},
+};
+
+/// Multiplies every delay by `factor`, 2 plays the animation at half speed.
+///
+/// Only the graphic control extensions change. Delays are rounded to whole
+/// centiseconds against the scaled running time, so rounding errors do not
+/// add up over the animation.
+pub fn scale_delays(gif: &Gif, factor: f64) -> Result<Gif, TransformError> {
+    if !factor.is_finite() || factor <= 0.0 {
+        return Err(TransformError::InvalidData(
+            format!("delay factor must be positive, got {factor}").into(),
+        ));
+    }
+
+    let mut elapsed = 0u64;
+    let ends = delays(gif).map(|delay_time| {
+        elapsed += delay_time as u64;
+        elapsed as f64 * factor
+    });
+
+    with_delays(gif, ends)
+}
+
+/// Shows every frame for the same time, `fps` frames per second on average
+pub fn set_fps(gif: &Gif, fps: f64) -> Result<Gif, TransformError> {
+    let frame_time = frame_time(fps)?;
+    let ends = (1..=delays(gif).count()).map(|frame| frame as f64 * frame_time);
+
+    with_delays(gif, ends)
+}
+
+/// Samples the animation `fps` times per second, at most 100 since delays
+/// are whole centiseconds.
+///
+/// Each sample shows the frame that is on screen longest within its time
+/// window, the earliest one on ties. Other frames are dropped and repeated
+/// samples are merged into one frame. The total duration stays the same.
+pub fn resample_fps(gif: &Gif, fps: f64) -> Result<Gif, TransformError> {
+    let frame_time = frame_time(fps)?;
+    if fps > MAX_FPS {
+        return Err(TransformError::InvalidData(
+            format!("can not resample to more than {MAX_FPS} frames per second, got {fps}").into(),
+        ));
+    }
+    let mut animation = Animation::coalesce(gif)?;
+
+    let timeline = Timeline::from_delays(
+        animation.frames.iter().map(|frame| frame.delay_time),
+        DelayPolicy::Exact,
+    );
+    let duration = timeline.duration().as_millis() as f64 / 10.0;
+    if duration == 0.0 {
+        return Err(TransformError::InvalidData(
+            "can not resample an animation without delays".into(),
+        ));
+    }
+
+    let samples = (duration / frame_time).ceil() as usize;
+    let ends = (1..=samples).map(|sample| (sample as f64 * frame_time).min(duration));
+    let delays: Vec<u16> = Rounded::new(ends).collect::<Result<_, _>>()?;
+
+    // windows in whole microseconds, so bounds on a frame boundary do not
+    // land just before it
+    let micros = |time: Duration| time.as_micros() as u64;
+    let starts: Vec<u64> = timeline.start_times().iter().copied().map(micros).collect();
+    let end_of_animation = micros(timeline.duration());
+    let bound =
+        |sample: usize| ((sample as f64 * 1_000_000.0 / fps).round() as u64).min(end_of_animation);
+    let frame_end = |frame_idx: usize| {
+        starts
+            .get(frame_idx + 1)
+            .copied()
+            .unwrap_or(end_of_animation)
+    };
+
+    let mut first = 0;
+    let mut frames = Vec::with_capacity(delays.len());
+    for (sample, delay_time) in delays.into_iter().enumerate() {
+        let (start, end) = (bound(sample), bound(sample + 1));
+        while first + 1 < starts.len() && frame_end(first) <= start {
+            first += 1;
+        }
+        // samples rounded down to nothing are never on screen
+        if delay_time == 0 {
+            continue;
+        }
+
+        let shown = (first..starts.len())
+            .take_while(|frame_idx| starts[*frame_idx] < end)
+            .max_by_key(|frame_idx| {
+                let covered = frame_end(*frame_idx)
+                    .min(end)
+                    .saturating_sub(starts[*frame_idx].max(start));
+                (covered, Reverse(*frame_idx))
+            })
+            .unwrap_or(first);
+        let mut frame = animation.frames[shown].clone();
+        frame.delay_time = delay_time;
+        frames.push(frame);
+    }
+    animation.frames = frames;
+
+    animation.encode()
+}
+
+/// Samples per second [`resample_fps`] can show, one per centisecond
+const MAX_FPS: f64 = 100.0;
+
+/// Centiseconds per frame at `fps`
+fn frame_time(fps: f64) -> Result<f64, TransformError> {
+    if !fps.is_finite() || fps <= 0.0 {
+        return Err(TransformError::InvalidData(
+            format!("frame rate must be positive, got {fps}").into(),
+        ));
+    }
+
+    Ok(100.0 / fps)
+}
+
+fn delays(gif: &Gif) -> impl Iterator<Item = u16> {
+    gif.images()
+        .map(|(_, block, _)| block.gce.as_ref().map_or(0, |gce| gce.delay_time()))
+}
+
+/// Sets the delays so frames end at the rounded `ends`, in centiseconds
+fn with_delays(gif: &Gif, ends: impl Iterator<Item = f64>) -> Result<Gif, TransformError> {
+    let delays: Vec<u16> = Rounded::new(ends).collect::<Result<_, _>>()?;
+    let mut retimed = gif.clone();
+
+    for (gce, delay_time) in retimed.graphic_controls_or_default().zip(delays) {
+        gce.set_delay_time(delay_time);
+    }
+
+    Ok(retimed)
+}
+
+/// Whole centisecond delays between running times, each delay takes up the
+/// rounding error of the ones before it. Delays that do not fit into a frame
+/// are an error.
+struct Rounded<I> {
+    ends: I,
+    elapsed: u64,
+}
+
+impl<I: Iterator<Item = f64>> Rounded<I> {
+    fn new(ends: I) -> Self {
+        Self { ends, elapsed: 0 }
+    }
+}
+
+impl<I: Iterator<Item = f64>> Iterator for Rounded<I> {
+    type Item = Result<u16, TransformError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let end = (self.ends.next()?.round() as u64).max(self.elapsed);
+        let delay_time = end - self.elapsed;
+        self.elapsed = end;
+
+        Some(u16::try_from(delay_time).map_err(|_| {
+            TransformError::InvalidData(
+                format!("a delay of {delay_time} centiseconds does not fit into a frame").into(),
+            )
+        }))
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::encoder::Encoder;
+    use crate::transform::rendered;
+
+    fn animation(delays: &[u16]) -> Gif {
+        let mut encoder = Encoder::new(1, 1);
+        for (frame, delay_time) in delays.iter().enumerate() {
+            let shade = (frame % 5) as u8 * 50;
+            encoder.add_frame(&[shade, 0, 0, 255], *delay_time).unwrap();
+        }
+
+        encoder.encode().unwrap()
+    }
+
+    fn delays_of(gif: &Gif) -> Vec<u16> {
+        delays(gif).collect()
+    }
+
+    #[test]
+    fn keep_rounding_errors_from_adding_up() {
+        let gif = animation(&[3, 3, 3]);
+
+        assert_eq!(delays_of(&scale_delays(&gif, 0.5).unwrap()), vec![2, 1, 2]);
+        assert_eq!(delays_of(&set_fps(&gif, 30.0).unwrap()), vec![3, 4, 3]);
+        assert_eq!(
+            delays_of(&set_fps(&animation(&[1; 300]), 30.0).unwrap())
+                .iter()
+                .map(|delay| *delay as u32)
+                .sum::<u32>(),
+            1000
+        );
+        assert!(scale_delays(&gif, 0.0).is_err());
+    }
+
+    #[test]
+    fn resample_by_time_windows() {
+        let gif = animation(&[10, 10, 10, 10]);
+        let shades = |gif: &Gif| -> Vec<(u8, u16)> {
+            rendered(gif)
+                .iter()
+                .map(|(pixels, delay_time)| (pixels[0][0], *delay_time))
+                .collect()
+        };
+
+        assert_eq!(
+            shades(&resample_fps(&gif, 5.0).unwrap()),
+            vec![(0, 20), (100, 20)]
+        );
+        assert_eq!(
+            shades(&resample_fps(&gif, 15.0).unwrap()),
+            vec![(0, 13), (50, 7), (100, 13), (150, 7)]
+        );
+        // the second frame covers most of the first window, though the
+        // first one is on screen at its start
+        assert_eq!(
+            shades(&resample_fps(&animation(&[2, 8, 10]), 10.0).unwrap()),
+            vec![(50, 10), (100, 10)]
+        );
+        assert!(resample_fps(&gif, 101.0).is_err());
+    }
+
+    #[test]
+    fn reject_delays_that_do_not_fit_into_a_frame() {
+        let gif = animation(&[60_000, 60_000]);
+
+        assert!(scale_delays(&gif, 2.0).is_err());
+        assert!(resample_fps(&gif, 0.001).is_err());
+        assert_eq!(
+            delays_of(&scale_delays(&gif, 1.09).unwrap()),
+            vec![65_400, 65_400]
+        );
+    }
+}
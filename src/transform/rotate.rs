@@ -0,0 +1,262 @@
+use super::{TransformError, crop};
+use crate::decoder::decode_indices;
+use crate::encoder::lzw_encode;
+use crate::gif::{Block, Gif, Image, ImageContent, Rect, descriptor::ImageDescriptor};
+
+/// Clockwise rotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flip {
+    /// Mirrors left and right
+    Horizontal,
+    /// Mirrors top and bottom
+    Vertical,
+}
+
+/// Rotates every frame of `gif` clockwise, swapping the canvas width and
+/// height for quarter turns. Color tables are kept exactly.
+pub fn rotate(gif: &Gif, rotation: Rotation) -> Result<Gif, TransformError> {
+    let mapping = match rotation {
+        Rotation::Deg90 => Mapping::Rotate90,
+        Rotation::Deg180 => Mapping::Rotate180,
+        Rotation::Deg270 => Mapping::Rotate270,
+    };
+
+    remap(gif, mapping)
+}
+
+/// Mirrors every frame of `gif`. Color tables are kept exactly.
+pub fn flip(gif: &Gif, flip: Flip) -> Result<Gif, TransformError> {
+    let mapping = match flip {
+        Flip::Horizontal => Mapping::FlipHorizontal,
+        Flip::Vertical => Mapping::FlipVertical,
+    };
+
+    remap(gif, mapping)
+}
+
+/// Where pixels of a `width * height` area move to
+#[derive(Debug, Clone, Copy)]
+enum Mapping {
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipHorizontal,
+    FlipVertical,
+}
+
+impl Mapping {
+    fn swaps_axes(self) -> bool {
+        matches!(self, Mapping::Rotate90 | Mapping::Rotate270)
+    }
+
+    fn size(self, width: u16, height: u16) -> (u16, u16) {
+        if self.swaps_axes() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Place of `rect` on the remapped `width * height` canvas, `rect` has
+    /// to be inside of it
+    fn rect(self, rect: Rect, width: u16, height: u16) -> Rect {
+        let from_right = width - rect.left - rect.width;
+        let from_bottom = height - rect.top - rect.height;
+        let (left, top) = match self {
+            Mapping::Rotate90 => (from_bottom, rect.left),
+            Mapping::Rotate180 => (from_right, from_bottom),
+            Mapping::Rotate270 => (rect.top, from_right),
+            Mapping::FlipHorizontal => (from_right, rect.top),
+            Mapping::FlipVertical => (rect.left, from_bottom),
+        };
+        let (width, height) = self.size(rect.width, rect.height);
+
+        Rect::new(left, top, width, height)
+    }
+
+    /// Pixel of a `width * height` area that ends up at `(x, y)`
+    fn source(self, (x, y): (usize, usize), width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Mapping::Rotate90 => (y, height - 1 - x),
+            Mapping::Rotate180 => (width - 1 - x, height - 1 - y),
+            Mapping::Rotate270 => (width - 1 - y, x),
+            Mapping::FlipHorizontal => (width - 1 - x, y),
+            Mapping::FlipVertical => (x, height - 1 - y),
+        }
+    }
+}
+
+/// Moves the indices of every image, plain text is left as it is
+fn remap(gif: &Gif, mapping: Mapping) -> Result<Gif, TransformError> {
+    let (width, height) = (gif.lsd.canvas_width, gif.lsd.canvas_height);
+
+    // frames reaching past the canvas are clipped to it first, the part
+    // outside is never shown and has no place on the remapped canvas
+    let canvas = Rect::new(0, 0, width, height);
+    let overflows = gif.images().any(|(_, _, image)| {
+        let rect = image.descriptor.rect();
+        rect.intersection(&canvas) != Some(rect)
+    });
+    let mut remapped = match overflows {
+        true => crop(gif, canvas)?,
+        false => gif.clone(),
+    };
+    (remapped.lsd.canvas_width, remapped.lsd.canvas_height) = mapping.size(width, height);
+
+    for block in &mut remapped.blocks {
+        if let Block::Image(image_block) = block
+            && let ImageContent::Image(image) = &mut image_block.content
+        {
+            remap_image(image, mapping, width, height)?;
+        }
+    }
+
+    Ok(remapped)
+}
+
+fn remap_image(
+    image: &mut Image,
+    mapping: Mapping,
+    width: u16,
+    height: u16,
+) -> Result<(), TransformError> {
+    let rect = image.descriptor.rect();
+    let indices = decode_indices(image)?;
+    let moved = mapping.rect(rect, width, height);
+
+    let (src_width, src_height) = (rect.width as usize, rect.height as usize);
+    let mut remapped = Vec::with_capacity(indices.len());
+    for y in 0..moved.height as usize {
+        for x in 0..moved.width as usize {
+            let (src_x, src_y) = mapping.source((x, y), src_width, src_height);
+            remapped.push(indices[src_y * src_width + src_x]);
+        }
+    }
+
+    let mut descriptor = ImageDescriptor::new(moved.left, moved.top, moved.width, moved.height);
+    descriptor.set_local_color_table(image.lct.as_ref());
+    image.descriptor = descriptor;
+    image.data = lzw_encode(&remapped, image.data.lzw_min_code_size);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::decoder::parse;
+    use crate::gif::Rgba;
+    use crate::transform::{moving_square, one_bit_gif, rendered};
+
+    /// Canvas pixels moved the way `mapping` moves them
+    fn expected(gif: &Gif, mapping: Mapping) -> Vec<(Vec<Rgba>, u16)> {
+        let (width, height) = (
+            gif.lsd.canvas_width as usize,
+            gif.lsd.canvas_height as usize,
+        );
+        let (new_width, new_height) = mapping.size(width as u16, height as u16);
+
+        rendered(gif)
+            .into_iter()
+            .map(|(pixels, delay_time)| {
+                let moved = (0..new_height as usize)
+                    .flat_map(|y| (0..new_width as usize).map(move |x| (x, y)))
+                    .map(|position| {
+                        let (x, y) = mapping.source(position, width, height);
+                        pixels[y * width + x]
+                    })
+                    .collect();
+                (moved, delay_time)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rotate_and_flip_every_frame() {
+        // a canvas that is not square shows swapped axes
+        let gif = moving_square();
+
+        let results = [
+            (rotate(&gif, Rotation::Deg90).unwrap(), Mapping::Rotate90),
+            (rotate(&gif, Rotation::Deg180).unwrap(), Mapping::Rotate180),
+            (rotate(&gif, Rotation::Deg270).unwrap(), Mapping::Rotate270),
+            (
+                flip(&gif, Flip::Horizontal).unwrap(),
+                Mapping::FlipHorizontal,
+            ),
+            (flip(&gif, Flip::Vertical).unwrap(), Mapping::FlipVertical),
+        ];
+
+        for (remapped, mapping) in results {
+            let remapped = parse(remapped.to_bytes().unwrap().as_slice()).unwrap();
+            assert_eq!(
+                (remapped.lsd.canvas_width, remapped.lsd.canvas_height),
+                mapping.size(7, 5)
+            );
+            assert!(
+                rendered(&remapped) == expected(&gif, mapping),
+                "{mapping:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn move_frames_within_the_canvas() {
+        let rect = Rect::new(1, 2, 3, 4);
+
+        assert_eq!(Mapping::Rotate90.rect(rect, 10, 8), Rect::new(2, 1, 4, 3));
+        assert_eq!(Mapping::Rotate270.rect(rect, 10, 8), Rect::new(2, 6, 4, 3));
+        assert_eq!(Mapping::Rotate180.rect(rect, 10, 8), Rect::new(6, 2, 3, 4));
+        assert_eq!(
+            Mapping::FlipHorizontal.rect(rect, 10, 8),
+            Rect::new(6, 2, 3, 4)
+        );
+        assert_eq!(
+            Mapping::FlipVertical.rect(rect, 10, 8),
+            Rect::new(1, 2, 3, 4)
+        );
+        assert_eq!(
+            Mapping::FlipVertical.rect(Rect::new(0, 0, 2, 1), 2, 3),
+            Rect::new(0, 2, 2, 1)
+        );
+    }
+
+    #[test]
+    fn rotate_frames_with_one_bit_codes() {
+        let gif = one_bit_gif();
+
+        let rotated = rotate(&gif, Rotation::Deg90).unwrap();
+
+        assert!(rendered(&rotated) == expected(&gif, Mapping::Rotate90));
+    }
+
+    #[test]
+    fn clip_frames_past_the_canvas() {
+        let mut gif = one_bit_gif();
+        gif.lsd.canvas_width = 3;
+        if let Some(Block::Image(image)) = gif.blocks.last_mut()
+            && let ImageContent::Image(image) = &mut image.content
+        {
+            image.descriptor.left = 2;
+        }
+
+        for mapping in [Mapping::FlipHorizontal, Mapping::Rotate90] {
+            let remapped = match mapping {
+                Mapping::FlipHorizontal => flip(&gif, Flip::Horizontal).unwrap(),
+                _ => rotate(&gif, Rotation::Deg90).unwrap(),
+            };
+
+            assert!(
+                rendered(&remapped) == expected(&gif, mapping),
+                "{mapping:?}"
+            );
+        }
+    }
+}
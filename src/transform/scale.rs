@@ -0,0 +1,231 @@
+use super::TransformError;
+use crate::decoder::decode_indices;
+use crate::encoder::lzw_encode;
+use crate::gif::{
+    Block, Gif, Image, ImageContent, descriptor::ImageDescriptor, extension::PlainTextExtension,
+};
+
+/// Scales `gif` to `width * height` by repeating or dropping color table
+/// indices, for pixel art that must keep its exact colors.
+///
+/// Every frame keeps its color tables, graphic control and place relative
+/// to the canvas, only its indices are sampled again. Nothing is composited
+/// or quantized, which makes this much faster than [`super::resize`].
+pub fn scale_indices(gif: &Gif, width: u16, height: u16) -> Result<Gif, TransformError> {
+    if width == 0 || height == 0 {
+        return Err(TransformError::InvalidData(
+            format!("can not scale to {width}x{height}").into(),
+        ));
+    }
+
+    let x = Axis::new(gif.lsd.canvas_width, width);
+    let y = Axis::new(gif.lsd.canvas_height, height);
+
+    let mut scaled = gif.clone();
+    scaled.lsd.canvas_width = width;
+    scaled.lsd.canvas_height = height;
+
+    for block in &mut scaled.blocks {
+        let Block::Image(image_block) = block else {
+            continue;
+        };
+
+        match &mut image_block.content {
+            ImageContent::Image(image) => scale_image(image, &x, &y)?,
+            ImageContent::PlainText(text) => scale_plain_text(text, &x, &y),
+        }
+    }
+
+    Ok(scaled)
+}
+
+/// Maps positions along one side of the canvas
+struct Axis {
+    from: u64,
+    to: u64,
+}
+
+impl Axis {
+    fn new(from: u16, to: u16) -> Self {
+        Self {
+            from: (from as u64).max(1),
+            to: to as u64,
+        }
+    }
+
+    /// Scaled start and length of `start..start + len`, never empty so
+    /// frames do not disappear
+    fn span(&self, start: u16, len: u16) -> (u16, u16) {
+        let scale = |pos: u64| (pos * self.to / self.from).min(u16::MAX as u64) as u16;
+        let scaled_start = scale(start as u64);
+        let scaled_end = scale(start as u64 + len as u64);
+
+        (scaled_start, (scaled_end - scaled_start).max(1))
+    }
+
+    /// Source position sampled for the center of `pos`
+    fn source(&self, pos: u16) -> u64 {
+        (2 * pos as u64 + 1) * self.from / (2 * self.to)
+    }
+}
+
+fn scale_image(image: &mut Image, x: &Axis, y: &Axis) -> Result<(), TransformError> {
+    let rect = image.descriptor.rect();
+    let (left, width) = x.span(rect.left, rect.width);
+    let (top, height) = y.span(rect.top, rect.height);
+
+    // frames without pixels have nothing to sample, they are only moved
+    if rect.width == 0 || rect.height == 0 {
+        image.descriptor.left = left;
+        image.descriptor.top = top;
+        return Ok(());
+    }
+
+    let indices = decode_indices(image)?;
+    // sampling in canvas coordinates keeps neighboring frames aligned
+    let offset = |axis: &Axis, pos: u16, start: u16, len: u16| {
+        axis.source(pos)
+            .clamp(start as u64, start as u64 + len as u64 - 1) as usize
+            - start as usize
+    };
+
+    let mut scaled = Vec::with_capacity(width as usize * height as usize);
+    for row in top..top + height {
+        let src_row = offset(y, row, rect.top, rect.height) * rect.width as usize;
+        for column in left..left + width {
+            scaled.push(indices[src_row + offset(x, column, rect.left, rect.width)]);
+        }
+    }
+
+    let mut descriptor = ImageDescriptor::new(left, top, width, height);
+    descriptor.set_local_color_table(image.lct.as_ref());
+    image.descriptor = descriptor;
+    image.data = lzw_encode(&scaled, image.data.lzw_min_code_size);
+
+    Ok(())
+}
+
+/// Scales the text grid, the header starts with its left, top, width and
+/// height followed by the cell width and height
+fn scale_plain_text(text: &mut PlainTextExtension, x: &Axis, y: &Axis) {
+    let header = &mut text.header;
+    if header.len() < 10 {
+        return;
+    }
+
+    let field = |header: &[u8], idx: usize| u16::from_le_bytes([header[idx], header[idx + 1]]);
+    let (left, width) = x.span(field(header, 0), field(header, 4));
+    let (top, height) = y.span(field(header, 2), field(header, 6));
+    let (cell_width, _) = x.span(0, header[8] as u16);
+    let (cell_height, _) = y.span(0, header[9] as u16);
+
+    for (idx, value) in [left, top, width, height].into_iter().enumerate() {
+        header[idx * 2..idx * 2 + 2].copy_from_slice(&value.to_le_bytes());
+    }
+    header[8] = cell_width.min(u8::MAX as u16) as u8;
+    header[9] = cell_height.min(u8::MAX as u16) as u8;
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::decoder::parse;
+    use crate::gif::{
+        Color, Rect,
+        builder::{FrameBuilder, GifBuilder},
+    };
+    use crate::transform::{moving_square, one_bit_gif, rendered};
+
+    #[test]
+    fn scale_indices_and_keep_the_tables() {
+        let palette = vec![
+            Color::from_triple((255, 0, 0)),
+            Color::from_triple((0, 0, 255)),
+        ];
+        let gif = GifBuilder::new(2, 2)
+            .global_palette(palette)
+            .frame(&FrameBuilder::new(2, 2, vec![0, 1, 1, 1]))
+            .frame(
+                FrameBuilder::new(1, 1, vec![0])
+                    .position(1, 1)
+                    .delay_time(20),
+            )
+            .build()
+            .unwrap();
+
+        let scaled = scale_indices(&gif, 4, 6).unwrap();
+
+        let scaled = parse(scaled.to_bytes().unwrap().as_slice()).unwrap();
+        let colors = |gif: &Gif| gif.gct.as_ref().map(|gct| gct.colors().to_vec());
+        assert_eq!(colors(&scaled), colors(&gif));
+        let descriptors: Vec<_> = scaled
+            .images()
+            .map(|(_, _, image)| image.descriptor.rect())
+            .collect();
+        assert_eq!(descriptors[1], Rect::new(2, 3, 2, 3));
+
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let frames = rendered(&scaled);
+        let upscaled = |row: [[u8; 4]; 2]| [row[0], row[0], row[1], row[1]];
+        let top = upscaled([red, blue]);
+        let bottom = upscaled([blue, red]);
+        let expected = [top, top, top, bottom, bottom, bottom].concat();
+        assert_eq!(frames[1], (expected, 20));
+    }
+
+    #[test]
+    fn keep_every_frame_when_shrinking() {
+        let gif = moving_square();
+
+        let scaled = scale_indices(&gif, 2, 1).unwrap();
+
+        assert_eq!(scaled.images().count(), gif.images().count());
+        assert!(
+            scaled
+                .images()
+                .all(|(_, _, image)| image.descriptor.rect().right() <= 2
+                    && image.descriptor.rect().bottom() <= 1)
+        );
+        assert_eq!(rendered(&scaled).len(), rendered(&gif).len());
+    }
+
+    #[test]
+    fn scale_frames_with_one_bit_codes() {
+        let gif = one_bit_gif();
+        let [red, blue] = [[255, 0, 0, 255], [0, 0, 255, 255]];
+        assert!(rendered(&gif) == vec![(vec![red, blue], 0)]);
+
+        let scaled = scale_indices(&gif, 4, 1).unwrap();
+
+        assert!(rendered(&scaled) == vec![(vec![red, red, blue, blue], 0)]);
+    }
+
+    #[test]
+    fn move_frames_without_pixels() {
+        let mut gif = one_bit_gif();
+        let mut empty = gif.images().next().unwrap().1.clone();
+        if let ImageContent::Image(image) = &mut empty.content {
+            image.descriptor = ImageDescriptor::new(1, 0, 0, 0);
+            image.data = lzw_encode(&[], 2);
+        }
+        gif.blocks.push(Block::Image(empty));
+
+        let scaled = scale_indices(&gif, 4, 4).unwrap();
+
+        let rects: Vec<Rect> = scaled
+            .images()
+            .map(|(_, _, image)| image.descriptor.rect())
+            .collect();
+        assert_eq!(rects, vec![Rect::new(0, 0, 4, 4), Rect::new(2, 0, 0, 0)]);
+        assert_eq!(rendered(&scaled).len(), 2);
+    }
+
+    #[test]
+    fn sample_large_canvases() {
+        let axis = Axis::new(50_000, 60_000);
+
+        assert_eq!(axis.source(59_999), 49_999);
+        assert_eq!(axis.span(40_000, 10_000), (48_000, 12_000));
+    }
+}
@@ -0,0 +1,270 @@
+use std::ops::{Bound, RangeBounds};
+
+use super::{Animation, TransformError};
+use crate::decoder::CompositedFrame;
+use crate::gif::{Gif, timeline::merged_delays};
+
+/// Plays the frames of `gif` from the last to the first
+pub fn reverse(gif: &Gif) -> Result<Gif, TransformError> {
+    let mut animation = Animation::coalesce(gif)?;
+    animation.frames.reverse();
+
+    animation.encode()
+}
+
+/// Plays the frames forward and then backward, the first and last frame are
+/// not repeated at the turns so the animation loops smoothly
+pub fn ping_pong(gif: &Gif) -> Result<Gif, TransformError> {
+    let mut animation = Animation::coalesce(gif)?;
+    let len = animation.frames.len();
+    let backward: Vec<_> = animation
+        .frames
+        .iter()
+        .rev()
+        .skip(1)
+        .take(len.saturating_sub(2))
+        .cloned()
+        .collect();
+    animation.frames.extend(backward);
+
+    animation.encode()
+}
+
+/// Keeps every `n`-th frame starting with the first, dropped frames add their
+/// delay to the kept frame before them so the duration stays the same. A
+/// kept frame is repeated where its delay would no longer fit into a frame.
+pub fn every_nth(gif: &Gif, n: usize) -> Result<Gif, TransformError> {
+    if n == 0 {
+        return Err(TransformError::InvalidData(
+            "frame step must be at least 1".into(),
+        ));
+    }
+
+    let mut animation = Animation::coalesce(gif)?;
+    animation.frames = animation
+        .frames
+        .chunks(n)
+        .flat_map(|frames| {
+            merged_delays(frames.iter().map(|frame| frame.delay_time))
+                .into_iter()
+                .map(|delay_time| CompositedFrame {
+                    delay_time,
+                    ..frames[0].clone()
+                })
+        })
+        .collect();
+
+    animation.encode()
+}
+
+/// Removes the frames in `range`, fails if no frame would be left
+pub fn remove_range(gif: &Gif, range: impl RangeBounds<usize>) -> Result<Gif, TransformError> {
+    let mut animation = Animation::coalesce(gif)?;
+    let len = animation.frames.len();
+
+    let start = match range.start_bound() {
+        Bound::Included(start) => *start,
+        Bound::Excluded(start) => start.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(end) => end.saturating_add(1),
+        Bound::Excluded(end) => *end,
+        Bound::Unbounded => len,
+    };
+    if start > end || end > len {
+        return Err(TransformError::InvalidData(
+            format!("frames {start}..{end} are out of the {len} frames").into(),
+        ));
+    }
+
+    animation.frames.drain(start..end);
+
+    animation.encode()
+}
+
+/// Plays `gifs` one after another.
+///
+/// The canvas grows to fit the largest GIF and smaller ones are centered on
+/// it. The loop count and extensions of the first GIF are kept, its plain
+/// text only if the canvas does not grow.
+pub fn concat(gifs: &[Gif]) -> Result<Gif, TransformError> {
+    let animations = gifs
+        .iter()
+        .map(Animation::coalesce)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let width = animations.iter().map(|animation| animation.width).max();
+    let height = animations.iter().map(|animation| animation.height).max();
+    let (Some(width), Some(height)) = (width, height) else {
+        return Err(TransformError::InvalidData("no GIFs to concatenate".into()));
+    };
+
+    let mut animations = animations
+        .into_iter()
+        .map(|animation| animation.with_canvas(width, height));
+    let mut joined = animations.next().expect("there is at least one GIF");
+    for animation in animations {
+        joined.frames.extend(animation.frames);
+    }
+
+    joined.encode()
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::encoder::{Encoder, Rgba};
+    use crate::transform::{moving_square, rendered};
+
+    const RED: Rgba = [255, 0, 0, 255];
+    const GREEN: Rgba = [0, 255, 0, 255];
+    const BLUE: Rgba = [0, 0, 255, 255];
+
+    /// One frame per color, each with a moving dot so frames are deltas
+    fn dots(size: u16, colors: &[Rgba]) -> Gif {
+        let mut encoder = Encoder::new(size, size);
+        encoder.loop_count(0);
+        for (frame, color) in colors.iter().enumerate() {
+            let mut pixels = vec![[255, 255, 255, 255]; size as usize * size as usize];
+            pixels[frame] = *color;
+            encoder
+                .add_frame(&pixels.concat(), frame as u16 + 1)
+                .unwrap();
+        }
+
+        encoder.encode().unwrap()
+    }
+
+    #[test]
+    fn reverse_the_coalesced_frames() {
+        let gif = moving_square();
+
+        let reversed = reverse(&gif).unwrap();
+
+        let mut expected = rendered(&gif);
+        expected.reverse();
+        assert!(rendered(&reversed) == expected);
+        assert_eq!(reversed.loop_count(), gif.loop_count());
+    }
+
+    #[test]
+    fn reorder_and_drop_frames() {
+        let gif = dots(4, &[RED, GREEN, BLUE, RED]);
+        let frames = rendered(&gif);
+
+        let delays = |gif: &Gif| -> Vec<u16> { rendered(gif).iter().map(|f| f.1).collect() };
+        assert_eq!(delays(&ping_pong(&gif).unwrap()), vec![1, 2, 3, 4, 3, 2]);
+        assert_eq!(delays(&every_nth(&gif, 3).unwrap()), vec![6, 4]);
+
+        let removed = rendered(&remove_range(&gif, 1..3).unwrap());
+        assert!(removed == vec![frames[0].clone(), frames[3].clone()]);
+        assert!(remove_range(&gif, ..).is_err());
+        assert!(remove_range(&gif, 2..5).is_err());
+    }
+
+    #[test]
+    fn repeat_kept_frames_too_long_for_one_delay() {
+        let mut encoder = Encoder::new(1, 1);
+        for (color, delay_time) in [(RED, 60_000), (GREEN, 60_000), (BLUE, 10), (GREEN, 5)] {
+            encoder.add_frame(&color, delay_time).unwrap();
+        }
+        let gif = encoder.encode().unwrap();
+
+        let kept = every_nth(&gif, 3).unwrap();
+
+        assert!(
+            rendered(&kept) == vec![(vec![RED], 60_000), (vec![RED], 60_010), (vec![GREEN], 5)]
+        );
+    }
+
+    #[test]
+    fn carry_extensions_along() {
+        use crate::gif::{
+            Block, ImageContent, SubBlock,
+            extension::{
+                ApplicationExtension, CommentExtension, PlainTextExtension,
+                UnknownApplicationExtension,
+            },
+        };
+
+        let mut gif = dots(2, &[RED, GREEN, BLUE]);
+        let comment =
+            |text: &[u8]| Block::CommentExtension(CommentExtension::new(SubBlock::chunks(text)));
+        let application = Block::ApplicationExtension(ApplicationExtension::Other(
+            UnknownApplicationExtension::from_tuple((*b"XMP Data", *b"XMP", vec![])),
+        ));
+        let plain_text = Block::image_block_from_tuple((
+            None,
+            ImageContent::plain_text(PlainTextExtension::from_tuple((vec![0; 12], vec![]))),
+        ));
+        let image = |idx: usize| gif.images().nth(idx).unwrap().1.clone();
+        let (first, second, third) = (image(0), image(1), image(2));
+        gif.blocks = vec![
+            comment(b"first"),
+            Block::Image(first),
+            application,
+            plain_text,
+            Block::Image(second),
+            Block::Image(third),
+            comment(b"last"),
+        ];
+        let kinds = |gif: &Gif| -> String {
+            gif.blocks
+                .iter()
+                .map(|block| match block {
+                    Block::Image(image) if matches!(image.content, ImageContent::PlainText(_)) => {
+                        'P'
+                    }
+                    Block::Image(_) => 'I',
+                    Block::ApplicationExtension(_) => 'A',
+                    Block::CommentExtension(_) => 'C',
+                })
+                .collect()
+        };
+
+        assert_eq!(kinds(&every_nth(&gif, 1).unwrap()), "CIAPIIC");
+        let reversed = reverse(&gif).unwrap();
+        assert_eq!(kinds(&reversed), "CAPIIIC");
+        assert_eq!(
+            reversed.comments().collect::<Vec<_>>(),
+            vec![b"first".to_vec(), b"last".to_vec()]
+        );
+    }
+
+    #[test]
+    fn concat_gifs_of_different_sizes() {
+        let small = dots(2, &[RED]);
+        let large = dots(4, &[GREEN, BLUE]);
+
+        let joined = concat(&[small, large.clone()]).unwrap();
+
+        assert_eq!((joined.lsd.canvas_width, joined.lsd.canvas_height), (4, 4));
+        let frames = rendered(&joined);
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].0[4 + 1], RED);
+        assert_eq!(frames[0].0[0], [0, 0, 0, 0]);
+        assert!(frames[1..] == rendered(&large)[..]);
+    }
+
+    #[test]
+    fn keep_every_color_of_full_tables() {
+        // 256 colors that all move in the second frame, the last pixels
+        // repeat colors and stay in place
+        let color = |idx: usize| [idx as u8, 255 - idx as u8, 7, 255];
+        let first: Vec<Rgba> = (0..17 * 16).map(|idx| color(idx % 256)).collect();
+        let mut second = first.clone();
+        for (idx, pixel) in second.iter_mut().take(256).enumerate() {
+            *pixel = color((idx + 1) % 256);
+        }
+        let mut encoder = Encoder::new(17, 16);
+        encoder.add_frame(&first.concat(), 10).unwrap();
+        encoder.add_frame(&second.concat(), 20).unwrap();
+        let gif = encoder.encode().unwrap();
+
+        let reversed = reverse(&gif).unwrap();
+
+        assert!(rendered(&gif) == vec![(first.clone(), 10), (second.clone(), 20)]);
+        assert!(rendered(&reversed) == vec![(second, 20), (first, 10)]);
+    }
+}
@@ -0,0 +1,258 @@
+use std::time::Duration;
+
+use super::{Animation, TransformError};
+use crate::decoder::Compositor;
+use crate::encoder::IndexedFrame;
+use crate::gif::{Block, Color, Gif, Rect, extension::DisposalMethod};
+
+const CENTISECOND: Duration = Duration::from_millis(10);
+
+/// Cuts the animation down to the frames shown from `start` until `end`,
+/// both rounded to whole centiseconds.
+///
+/// Kept frames keep their image data, except the first one. It is drawn
+/// again over the whole canvas, like the frames after it until one stays on
+/// screen, so nothing depends on the frames cut away. Frames cut by the
+/// window are shown for the part inside it, `end` past the animation keeps
+/// everything after `start`. When a redrawn frame needs more than 256
+/// colors, all kept frames are coalesced and encoded again.
+pub fn trim(gif: &Gif, start: Duration, end: Duration) -> Result<Gif, TransformError> {
+    let duration = gif.duration();
+    let window = (
+        whole_centiseconds(start),
+        whole_centiseconds(end.min(duration)),
+    );
+
+    // frames shown at `start` and right before `end`, frames without a delay
+    // in between are kept for what they draw
+    let frames = (window.0 < window.1)
+        .then(|| {
+            gif.frame_index_at(window.0)
+                .zip(gif.frame_index_at(window.1 - CENTISECOND))
+        })
+        .flatten();
+    let Some((first, last)) = frames else {
+        return Err(TransformError::InvalidData(
+            format!("{start:?}..{end:?} is outside the {duration:?} animation").into(),
+        ));
+    };
+    let (start, end) = window;
+
+    let starts = gif.frame_start_times();
+    let delays: Vec<u16> = (first..=last)
+        .map(|frame_idx| {
+            let frame_end = starts.get(frame_idx + 1).copied().unwrap_or(duration);
+            let shown = frame_end
+                .min(end)
+                .saturating_sub(starts[frame_idx].max(start));
+            (shown.as_millis() / 10) as u16
+        })
+        .collect();
+
+    let Some(keyframes) = keyframes(gif, first, last, &delays)? else {
+        return coalesced(gif, first, &delays);
+    };
+
+    let images: Vec<usize> = gif.images().map(|(block_idx, _, _)| block_idx).collect();
+    let kept = images[first]..=images[last];
+    let mut trimmed = gif.clone();
+    trimmed.blocks = gif
+        .blocks
+        .iter()
+        .enumerate()
+        .filter(|(block_idx, block)| !matches!(block, Block::Image(_)) || kept.contains(block_idx))
+        .map(|(block_idx, block)| {
+            let keyframe = images
+                .binary_search(&block_idx)
+                .ok()
+                .and_then(|frame_idx| keyframes.get(frame_idx - first));
+            keyframe.unwrap_or(block).clone()
+        })
+        .collect();
+
+    for (frame_idx, delay_time) in delays.into_iter().enumerate() {
+        // delays only get shorter, frames without a graphic control stay at 0
+        if let Some(gce) = trimmed.graphic_control_mut(frame_idx) {
+            gce.set_delay_time(delay_time);
+        }
+    }
+
+    Ok(trimmed)
+}
+
+fn whole_centiseconds(time: Duration) -> Duration {
+    let centiseconds = (time.as_nanos() + CENTISECOND.as_nanos() / 2) / CENTISECOND.as_nanos();
+
+    Duration::from_millis(u64::try_from(centiseconds * 10).unwrap_or(u64::MAX))
+}
+
+/// Frames from `first` on drawn over the whole canvas, until one stays on
+/// screen for the frames after it to draw onto. `None` if a frame needs more
+/// than 256 colors.
+fn keyframes(
+    gif: &Gif,
+    first: usize,
+    last: usize,
+    delays: &[u16],
+) -> Result<Option<Vec<Block>>, TransformError> {
+    let canvas = Rect::new(0, 0, gif.lsd.canvas_width, gif.lsd.canvas_height);
+    let mut keyframes = Vec::new();
+
+    for (frame_idx, frame) in Compositor::new(gif).enumerate().take(last + 1).skip(first) {
+        let frame = frame?;
+        let Block::Image(image_block) = &gif.blocks[frame.block_idx] else {
+            unreachable!("frames are drawn from image blocks");
+        };
+        let gce = image_block.gce.as_ref();
+        let stays = frame_idx == last
+            || matches!(
+                gce.map(|gce| gce.disposal_method()).unwrap_or_default(),
+                DisposalMethod::Unspecified | DisposalMethod::Keep
+            );
+
+        let pixels: Vec<Option<Color>> = frame
+            .pixels
+            .iter()
+            .map(|[r, g, b, a]| {
+                (*a != 0).then_some(Color {
+                    r: *r,
+                    g: *g,
+                    b: *b,
+                })
+            })
+            .collect();
+        // the next keyframe has to be drawn onto a clear canvas
+        let disposal = match stays {
+            true => DisposalMethod::Keep,
+            false => DisposalMethod::RestoreBackground,
+        };
+        let Some(keyframe) =
+            IndexedFrame::from_pixels(canvas, delays[frame_idx - first], disposal, &pixels)
+        else {
+            return Ok(None);
+        };
+        keyframes.push(keyframe.local_block(gce.is_some_and(|gce| gce.user_input())));
+
+        if stays {
+            break;
+        }
+    }
+
+    Ok(Some(keyframes))
+}
+
+/// Kept frames coalesced and encoded again, frames without a delay are
+/// never on screen and dropped
+fn coalesced(gif: &Gif, first: usize, delays: &[u16]) -> Result<Gif, TransformError> {
+    let mut animation = Animation::coalesce(gif)?;
+    animation.frames = animation
+        .frames
+        .drain(first..first + delays.len())
+        .zip(delays)
+        .filter(|(_, delay_time)| **delay_time > 0)
+        .map(|(mut frame, delay_time)| {
+            frame.delay_time = *delay_time;
+            frame
+        })
+        .collect();
+
+    animation.encode()
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::encoder::{Encoder, Rgba};
+    use crate::transform::rendered;
+
+    /// Dot moving along the top row, every frame but the first is a delta
+    fn moving_dot() -> Gif {
+        let mut encoder = Encoder::new(4, 2);
+        for frame in 0..4 {
+            let mut pixels = [[255, 255, 255, 255]; 8];
+            pixels[frame] = [255, 0, 0, 255];
+            encoder.add_frame(&pixels.concat(), 10).unwrap();
+        }
+
+        encoder.encode().unwrap()
+    }
+
+    /// Rendered frames of `gif` shown for the given delays
+    fn expected(gif: &Gif, frames: &[(usize, u16)]) -> Vec<(Vec<Rgba>, u16)> {
+        let original = rendered(gif);
+
+        frames
+            .iter()
+            .map(|(frame, delay_time)| (original[*frame].0.clone(), *delay_time))
+            .collect()
+    }
+
+    #[test]
+    fn start_with_a_keyframe_and_shorten_cut_frames() {
+        let gif = moving_dot();
+
+        let trimmed = trim(&gif, Duration::from_millis(150), Duration::from_millis(320)).unwrap();
+
+        let first = trimmed.images().next().unwrap().2;
+        assert_eq!(first.descriptor.rect(), Rect::new(0, 0, 4, 2));
+        for ((_, _, trimmed), (_, _, original)) in
+            trimmed.images().zip(gif.images().skip(1)).skip(1)
+        {
+            assert_eq!(trimmed.data.sub_blocks, original.data.sub_blocks);
+        }
+        assert!(rendered(&trimmed) == expected(&gif, &[(1, 5), (2, 10), (3, 2)]));
+    }
+
+    #[test]
+    fn cut_at_whole_centiseconds() {
+        let gif = moving_dot();
+
+        let trimmed = trim(&gif, Duration::from_millis(94), Duration::from_millis(304)).unwrap();
+
+        assert!(rendered(&trimmed) == expected(&gif, &[(0, 1), (1, 10), (2, 10)]));
+    }
+
+    #[test]
+    fn redraw_frames_until_one_stays_on_screen() {
+        let mut gif = moving_dot();
+        gif.graphic_control_mut(1)
+            .unwrap()
+            .set_disposal_method(DisposalMethod::RestoreBackground);
+
+        let trimmed = trim(&gif, Duration::from_millis(100), Duration::from_millis(400)).unwrap();
+
+        let rects: Vec<Rect> = trimmed
+            .images()
+            .map(|(_, _, image)| image.descriptor.rect())
+            .collect();
+        assert_eq!(rects[..2], [Rect::new(0, 0, 4, 2); 2]);
+        assert!(rendered(&trimmed) == expected(&gif, &[(1, 10), (2, 10), (3, 10)]));
+    }
+
+    #[test]
+    fn reject_windows_without_frames() {
+        let gif = moving_dot();
+        let trim = |start: u64, end: u64| {
+            trim(
+                &gif,
+                Duration::from_millis(start),
+                Duration::from_millis(end),
+            )
+        };
+
+        assert!(trim(400, 500).is_err());
+        assert!(trim(200, 200).is_err());
+        assert_eq!(rendered(&trim(300, 900).unwrap()).len(), 1);
+    }
+
+    #[test]
+    fn keep_everything_before_an_end_past_the_animation() {
+        let gif = moving_dot();
+
+        let trimmed = trim(&gif, Duration::ZERO, Duration::MAX).unwrap();
+
+        assert!(rendered(&trimmed) == rendered(&gif));
+        let error = trim(&gif, Duration::MAX, Duration::MAX).err().unwrap();
+        assert!(error.to_string().contains(&format!("{:?}", Duration::MAX)));
+    }
+}